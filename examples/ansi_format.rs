@@ -0,0 +1,8 @@
+use procr_ansi_term::{ansi_eprintln, ansi_format, ansi_println, Color};
+
+fn main() {
+    let name = "world";
+    let greeting = ansi_format!("hello {red.bold:name}");
+    ansi_println!("[italic]{}[/], printed to stdout", greeting);
+    ansi_eprintln!("[dim]and {} to stderr[/]", Color::Cyan.paint(name));
+}
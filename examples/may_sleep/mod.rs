@@ -15,10 +15,7 @@ pub fn parse_cmd_args() -> Option<u16> {
                     .unwrap_or(String::from("5000u16"))
                     .parse::<u16>()
                     .ok()
-                    .map(|parsed| {
-                        skip_next = true;
-                        parsed
-                    });
+                    .inspect(|_| skip_next = true);
             }
             _ => {}
         }
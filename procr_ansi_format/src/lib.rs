@@ -1,14 +1,17 @@
+// Ported from `rustc_parse_format`, so not all of it is used.
+#[allow(dead_code)]
 mod parse_fmt_str;
+mod style_spec;
 use std::cmp::Ordering;
 
-use parse_fmt_str::{Argument, Parser, Position};
+use parse_fmt_str::{Alignment, Argument, Count, FormatSpec, Parser, Piece, Position};
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{format_ident, quote, ToTokens};
-use rand::prelude::*;
+use style_spec::{ArgMarkup, Markup, StyleSpec};
 use syn::{
     ext::IdentExt,
     parse::{Parse, ParseStream},
-    parse_macro_input, Expr, Ident, LitStr, Result as SynResult, Token,
+    parse_macro_input, Error, Expr, Ident, LitStr, Result as SynResult, Token,
 };
 
 // pub struct AnsiFormatArgs<'a, F>
@@ -72,9 +75,21 @@ impl Parse for InputArgs {
             if input.is_empty() {
                 break;
             }
+            let explicit: InputExplicit = input.parse()?;
+            if let InputExplicit::Named { name, str_name, .. } = &explicit {
+                let duplicate = explicit_args.iter().any(|d: &DiscoveredExplicit| {
+                    matches!(&d.explicit, InputExplicit::Named { str_name: other, .. } if other == str_name)
+                });
+                if duplicate {
+                    return Err(Error::new(
+                        name.span(),
+                        format!("duplicate argument named `{}`", str_name),
+                    ));
+                }
+            }
             explicit_args.push(DiscoveredExplicit {
                 matched: false,
-                explicit: input.parse()?,
+                explicit,
             });
         }
 
@@ -92,11 +107,10 @@ enum ExplicitName {
 }
 
 impl ExplicitName {
-    fn new_random() -> Self {
-        ExplicitName::Generated({
-            let r: u64 = random();
-            format_ident!("x{}", r)
-        })
+    /// A name for the positional argument at the given position, which is
+    /// hygienic so that it can't clash with names at the call site.
+    fn new_positional(position: usize) -> Self {
+        ExplicitName::Generated(format_ident!("x{}", position, span = Span::mixed_site()))
     }
 
     fn id(&self) -> &Ident {
@@ -126,9 +140,9 @@ enum MatchResult {
 }
 
 impl MatchedExplicit {
-    fn with_random_name(position: usize, expr: Expr) -> Self {
+    fn with_positional_name(position: usize, expr: Expr) -> Self {
         Self {
-            name: ExplicitName::new_random(),
+            name: ExplicitName::new_positional(position),
             expr,
             position,
         }
@@ -142,35 +156,52 @@ impl MatchedExplicit {
         }
     }
 
+    /// Match a placeholder in the format string with its argument, or report
+    /// why it can't be matched (at the span of the placeholder).
     fn match_with_arg(
         argument: &Argument<'_>,
         explicits: &mut [DiscoveredExplicit],
-    ) -> MatchResult {
+        placeholder: Span,
+    ) -> SynResult<MatchResult> {
+        let count = explicits.len();
         match argument.position {
-            Position::ImplicitlyLocated(ix) | Position::IndexGiven(ix) => explicits
-                .get_mut(ix)
-                .map(|d: &mut DiscoveredExplicit| match &mut d.explicit {
+            Position::ImplicitlyLocated(ix) | Position::IndexGiven(ix) => {
+                let Some(d) = explicits.get_mut(ix) else {
+                    return Err(Error::new(
+                        placeholder,
+                        format!(
+                            "invalid reference to positional argument {} ({})",
+                            ix,
+                            argument_count(count)
+                        ),
+                    ));
+                };
+                match &d.explicit {
+                    InputExplicit::Positional { .. } if d.matched => {
+                        Ok(MatchResult::AlreadyMatched)
+                    }
                     InputExplicit::Positional { expr } => {
-                        if !d.matched {
-                            d.matched = true;
-                            MatchResult::MatchFound(MatchedExplicit::with_random_name(
-                                ix,
-                                expr.clone(),
-                            ))
-                        } else {
-                            MatchResult::AlreadyMatched
-                        }
+                        d.matched = true;
+                        Ok(MatchResult::MatchFound(
+                            MatchedExplicit::with_positional_name(ix, expr.clone()),
+                        ))
                     }
-                    InputExplicit::Named { .. } => {
-                        panic!("Expected a positional, unnamed argument.")
+                    InputExplicit::Named { str_name, .. } => {
+                        let message = format!(
+                            "named argument `{0}` is used by position, use `{{{0}}}` instead",
+                            str_name
+                        );
+                        // It is used, so don't report it as unused as well.
+                        d.matched = true;
+                        Err(Error::new(placeholder, message))
                     }
-                })
-                .expect("arguments without name should have some explicit match"),
-            Position::Named(this) => explicits
-                .iter()
+                }
+            }
+            Position::Named(this) => Ok(explicits
+                .iter_mut()
                 .enumerate()
                 .find_map(|(ix, d)| match &d.explicit {
-                    InputExplicit::Positional { .. } => panic!("Expected a named argument."),
+                    InputExplicit::Positional { .. } => None,
                     InputExplicit::Named {
                         name,
                         expr,
@@ -179,22 +210,26 @@ impl MatchedExplicit {
                         if d.matched {
                             MatchResult::AlreadyMatched
                         } else {
-                            MatchResult::MatchFound(MatchedExplicit::with_name(
-                                name.clone(),
-                                ix,
-                                expr.clone(),
-                            ))
+                            let found = MatchedExplicit::with_name(name.clone(), ix, expr.clone());
+                            d.matched = true;
+                            MatchResult::MatchFound(found)
                         }
                     }),
                 })
-                .unwrap_or(MatchResult::ImplicitNamed(Ident::new(
-                    this,
-                    Span::call_site(),
-                ))),
+                .unwrap_or(MatchResult::ImplicitNamed(Ident::new(this, placeholder)))),
         }
     }
 }
 
+/// How many arguments were given, as `format!` describes it in errors.
+fn argument_count(count: usize) -> String {
+    match count {
+        0 => "no arguments were given".to_string(),
+        1 => "there is 1 argument".to_string(),
+        n => format!("there are {} arguments", n),
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum Arg {
     Explicit(MatchedExplicit),
@@ -202,12 +237,18 @@ enum Arg {
 }
 
 impl Arg {
-    fn from_fmt_arg(arg: &Argument<'_>, explicits: &mut [DiscoveredExplicit]) -> Option<Arg> {
-        match MatchedExplicit::match_with_arg(arg, explicits) {
-            MatchResult::MatchFound(explicit) => Arg::Explicit(explicit).into(),
-            MatchResult::ImplicitNamed(name) => Arg::ImplicitNamed(name).into(),
-            MatchResult::AlreadyMatched => None,
-        }
+    fn from_fmt_arg(
+        arg: &Argument<'_>,
+        explicits: &mut [DiscoveredExplicit],
+        placeholder: Span,
+    ) -> SynResult<Option<Arg>> {
+        Ok(
+            match MatchedExplicit::match_with_arg(arg, explicits, placeholder)? {
+                MatchResult::MatchFound(explicit) => Arg::Explicit(explicit).into(),
+                MatchResult::ImplicitNamed(name) => Arg::ImplicitNamed(name).into(),
+                MatchResult::AlreadyMatched => None,
+            },
+        )
     }
 
     fn filter_implicit(&self) -> Option<&Ident> {
@@ -248,18 +289,69 @@ impl FormatArgs {
         self.args.iter().filter_map(Arg::filter_explicit)
     }
 
-    fn arg_tokens(&self) -> impl Iterator<Item = TokenStream2> + '_ {
+    /// The index in `render_inputs` of the argument at the given position.
+    fn input_ix(&self, position: Position<'_>) -> usize {
         self.args
             .iter()
-            .enumerate()
-            .filter_map(|(ix, arg)| match arg {
-                Arg::Explicit(MatchedExplicit { name, .. }) => match name {
-                    ExplicitName::Generated(_) => quote!(render_inputs[#ix]),
-                    ExplicitName::Given(_) => quote!(#name=render_inputs[#ix]),
+            .position(|arg| match (arg, position) {
+                (
+                    Arg::Explicit(explicit),
+                    Position::ImplicitlyLocated(ix) | Position::IndexGiven(ix),
+                ) => explicit.position == ix,
+                (Arg::Explicit(explicit), Position::Named(name)) => {
+                    matches!(&explicit.name, ExplicitName::Given(id) if id == name)
                 }
-                .into(),
-                Arg::ImplicitNamed(_) => None,
+                (Arg::ImplicitNamed(id), Position::Named(name)) => id == name,
+                _ => false,
             })
+            .expect("every argument in the format string has been matched")
+    }
+
+    /// Split the format string into the segments it renders, with the styles
+    /// given by its markup.
+    fn segments(&self, pieces: &[Piece<'_>], markup: &Markup) -> SynResult<Vec<Segment>> {
+        // Pieces of text are slices of the format string, so their offsets
+        // are found from where they start.
+        let base = markup.fmt_s.as_ptr() as usize;
+        let mut arg_markups = markup.args.iter();
+        let mut segments: Vec<Segment> = Vec::new();
+        for piece in pieces {
+            match piece {
+                Piece::String(text) => {
+                    let start = text.as_ptr() as usize - base;
+                    for (range, style) in markup.styles_in(start..start + text.len()) {
+                        let text = &markup.fmt_s[range];
+                        match segments.last_mut() {
+                            Some(Segment::Text(last, last_style)) if last_style == style => {
+                                last.push_str(text)
+                            }
+                            _ => segments.push(Segment::Text(text.to_string(), style.clone())),
+                        }
+                    }
+                }
+                Piece::NextArgument(arg) => {
+                    let ArgMarkup {
+                        offset,
+                        span,
+                        style,
+                        spec,
+                    } = arg_markups
+                        .next()
+                        .expect("markup found the same arguments as the parser");
+                    let format = if arg.format.ty.is_empty() {
+                        InputFormat::Display(Layout::new(&arg.format, *span)?)
+                    } else {
+                        InputFormat::Spec(spec.clone())
+                    };
+                    segments.push(Segment::Input {
+                        ix: self.input_ix(arg.position),
+                        style: style.rebase_on(markup.style_at(*offset)),
+                        format,
+                    });
+                }
+            }
+        }
+        Ok(segments)
     }
 
     fn string_exprs(&self) -> TokenStream2 {
@@ -267,101 +359,422 @@ impl FormatArgs {
             .explicit_args()
             .map(|explicit| explicit.expr.to_token_stream())
             .chain(self.inline_names().map(|name| name.to_token_stream()));
-        quote!(#(AnsiGenericString::from(#string_exprs)),*)
+        quote!(#(::procr_ansi_term::AnsiGenericString::from(#string_exprs)),*)
     }
 }
 
+/// Match the placeholders in the format string with their arguments,
+/// reporting every placeholder which can't be matched and every argument
+/// which is never used.
 fn extract_inline_named_args(
     mut explicit_args: Vec<DiscoveredExplicit>,
-    fmt_s: String,
-) -> FormatArgs {
-    FormatArgs::new(
-        Parser::new(&fmt_s, false)
-            .filter_map(|piece| match piece {
-                parse_fmt_str::Piece::String(_) => None,
-                parse_fmt_str::Piece::NextArgument(arg) => {
-                    Arg::from_fmt_arg(arg.as_ref(), &mut explicit_args)
+    pieces: &[Piece<'_>],
+    markup: &Markup,
+) -> SynResult<FormatArgs> {
+    let mut args: Vec<Arg> = Vec::new();
+    let mut errors: Option<Error> = None;
+    let mut push_error = |error: Error| match &mut errors {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
+    };
+
+    let placeholders = markup.args.iter().map(|arg| arg.span);
+    let arguments = pieces.iter().filter_map(|piece| match piece {
+        Piece::NextArgument(arg) => Some(arg),
+        Piece::String(_) => None,
+    });
+    let implicit_count = pieces
+        .iter()
+        .filter(|piece| {
+            matches!(piece, Piece::NextArgument(arg) if matches!(arg.position, Position::ImplicitlyLocated(_)))
+        })
+        .count();
+    let mut missing_reported = false;
+    for (arg, placeholder) in arguments.zip(placeholders) {
+        if let Position::ImplicitlyLocated(ix) = arg.position {
+            if ix >= explicit_args.len() {
+                // Like `format!`, only report the first missing argument.
+                if !missing_reported {
+                    missing_reported = true;
+                    push_error(Error::new(
+                        placeholder,
+                        format!(
+                            "{} positional argument{} in format string, but {}",
+                            implicit_count,
+                            if implicit_count == 1 { "" } else { "s" },
+                            argument_count(explicit_args.len())
+                        ),
+                    ));
                 }
-            })
-            .collect(),
-    )
+                continue;
+            }
+        }
+        // Arguments given as a width or precision count as used, so that the
+        // error is about the count instead.
+        for count in [arg.format.width, arg.format.precision] {
+            let counted = match count {
+                Count::ByParam(ix) | Count::Star(ix) => explicit_args.get_mut(ix),
+                Count::ByName(name, _) => explicit_args.iter_mut().find(|d| {
+                    matches!(&d.explicit, InputExplicit::Named { str_name, .. } if str_name == name)
+                }),
+                Count::Explicit(_) | Count::Implied => None,
+            };
+            if let Some(d) = counted {
+                d.matched = true;
+            }
+        }
+        match Arg::from_fmt_arg(arg.as_ref(), &mut explicit_args, placeholder) {
+            // An inline name used more than once is only captured once.
+            Ok(Some(arg @ Arg::ImplicitNamed(_))) if args.contains(&arg) => {}
+            Ok(Some(arg)) => args.push(arg),
+            Ok(None) => {}
+            Err(error) => push_error(error),
+        }
+    }
+
+    for d in explicit_args.iter().filter(|d| !d.matched) {
+        push_error(match &d.explicit {
+            InputExplicit::Positional { expr } => Error::new_spanned(expr, "argument never used"),
+            InputExplicit::Named { expr, .. } => {
+                Error::new_spanned(expr, "named argument never used")
+            }
+        });
+    }
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(FormatArgs::new(args)),
+    }
 }
 
-// Function like proc_macros have signature (TokenStream) -> TokenStream.
-#[proc_macro]
-pub fn ansi_format(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let InputArgs {
+/// The width, precision and alignment given for a displayed input. Unlike
+/// with `format!`, these are measured in the columns and characters of its
+/// visible text, rather than in the bytes of its escape sequences.
+struct Layout {
+    fill: char,
+    align: Alignment,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+impl Layout {
+    fn new(format: &FormatSpec<'_>, placeholder: Span) -> SynResult<Layout> {
+        let count = |count: Count<'_>| {
+            match count {
+            Count::Explicit(n) => Ok(Some(n)),
+            Count::Implied => Ok(None),
+            _ => Err(Error::new(
+                placeholder,
+                "the width and precision of ANSI string arguments must be given in the format string",
+            )),
+        }
+        };
+        Ok(Layout {
+            fill: format.fill.unwrap_or(' '),
+            align: format.align,
+            width: count(format.width)?,
+            precision: count(format.precision)?,
+        })
+    }
+}
+
+/// How an input is formatted.
+enum InputFormat {
+    /// Displayed, with the given layout.
+    Display(Layout),
+    /// Formatted with the given format spec (such as `?` for `Debug`).
+    Spec(String),
+}
+
+/// A part of the rendered string.
+enum Segment {
+    /// Literal text, in the style given by markup around it.
+    Text(String, StyleSpec),
+    /// An input, rebased on the style given by markup around it (or in its
+    /// own spec).
+    Input {
+        ix: usize,
+        style: StyleSpec,
+        format: InputFormat,
+    },
+}
+
+impl ToTokens for Segment {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.extend(match self {
+            Segment::Text(text, style) => quote!(rendered.push(#style.paint(#text));),
+            Segment::Input { ix, style, format } => {
+                let (input, owned_input) = if style.is_empty() {
+                    (
+                        quote!(render_inputs[#ix]),
+                        quote!(render_inputs[#ix].clone()),
+                    )
+                } else {
+                    let input = quote!(render_inputs[#ix].clone().rebase_on(#style));
+                    (input.clone(), input)
+                };
+                match format {
+                    InputFormat::Display(Layout {
+                        width: None,
+                        precision: None,
+                        ..
+                    }) => quote!(rendered.push(#owned_input);),
+                    InputFormat::Display(Layout {
+                        fill,
+                        align,
+                        width,
+                        precision,
+                    }) => {
+                        let precision = precision.map(|precision| {
+                            quote!(
+                                let strings = ::procr_ansi_term::utils::sub_string_by(
+                                    ::procr_ansi_term::utils::Unit::Chars, 0, #precision, &strings
+                                );
+                            )
+                        });
+                        // Padding is in the style given to the input, if any.
+                        let width = width.map(|width| {
+                            let pad = match align {
+                                Alignment::Right => quote!(pad_left),
+                                Alignment::Center => quote!(center),
+                                Alignment::Left | Alignment::Unknown => quote!(pad_right),
+                            };
+                            quote!(let strings = strings.#pad(#width, (#fill, #style));)
+                        });
+                        quote!({
+                            let strings = ::procr_ansi_term::AnsiStrings([#owned_input]);
+                            #precision
+                            #width
+                            rendered.push(::procr_ansi_term::AnsiGenericString::from(strings));
+                        })
+                    }
+                    InputFormat::Spec(spec) => {
+                        let fmt_s = format!("{{:{}}}", spec);
+                        quote!(rendered.push(
+                            ::procr_ansi_term::Style::new().paint(::std::format!(#fmt_s, #input))
+                        );)
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Expand to the given errors, in expression position.
+fn compile_errors(errors: Error) -> TokenStream2 {
+    let errors = errors.to_compile_error();
+    quote!({ #errors })
+}
+
+/// Expand to an expression creating a boxed `FmtRenderer` for the given
+/// format string and arguments (or to the errors found in them).
+fn expand_format(
+    InputArgs {
         fmt_s,
         explicit_args,
-    } = parse_macro_input!(input);
+    }: InputArgs,
+) -> TokenStream2 {
+    let markup = match Markup::parse(&fmt_s) {
+        Ok(markup) => markup,
+        Err(errors) => return compile_errors(errors),
+    };
+    let mut parser = Parser::new(&markup.fmt_s, false);
+    let pieces: Vec<Piece<'_>> = parser.by_ref().collect();
+    if let Some(error) = parser.errors.first() {
+        return compile_errors(Error::new(fmt_s.span(), &error.description));
+    }
 
-    let format_args = extract_inline_named_args(explicit_args, fmt_s.value());
-    let arg_tokens: Vec<TokenStream2> = format_args.arg_tokens().collect();
+    let format_args = match extract_inline_named_args(explicit_args, &pieces, &markup) {
+        Ok(format_args) => format_args,
+        Err(errors) => return compile_errors(errors),
+    };
+    let segments = match format_args.segments(&pieces, &markup) {
+        Ok(segments) => segments,
+        Err(errors) => return compile_errors(errors),
+    };
     let string_exprs = format_args.string_exprs();
-    let random_id = format_ident!("GenericFmtArgImplementor{}", random::<usize>());
-    let format_args = quote!(format!(#fmt_s, #(#arg_tokens),*));
-    let fmt_args_str = format!("Some({})", format_args);
-    let random_id_str = format!("{}", random_id);
+    let segment_count = segments.len();
+    // Every expansion is in its own block, and the name is hygienic, so it
+    // can't clash with other expansions or names at the call site. Nothing is
+    // imported into the block (which also holds the arguments), so everything
+    // else is referred to by its full path.
+    let renderer_id = Ident::new("GenericFmtArgImplementor", Span::mixed_site());
+    let renderer_id_str = renderer_id.to_string();
 
     quote! {
         {
-            use procr_ansi_term::{FmtRenderer, AnsiGenericString};
-            use std::fmt;
-
             #[derive(Default)]
-            struct #random_id<'a>  {
-                render_inputs: Vec<AnsiGenericString<'a, str>>,
+            struct #renderer_id<'a>  {
+                render_inputs: ::std::vec::Vec<::procr_ansi_term::AnsiGenericString<'a, str>>,
+                context: ::procr_ansi_term::Style,
             }
 
-            impl<'a> #random_id<'a>  {
-                fn new_boxed(render_inputs: Vec<AnsiGenericString<'a, str>>) -> Box<dyn FmtRenderer<str>> {
-                    Box::new(Self {
+            impl<'a> #renderer_id<'a>  {
+                fn new_boxed(render_inputs: ::std::vec::Vec<::procr_ansi_term::AnsiGenericString<'a, str>>) -> ::std::boxed::Box<dyn ::procr_ansi_term::FmtRenderer<'a, str>> {
+                    ::std::boxed::Box::new(Self {
                         render_inputs,
+                        context: ::procr_ansi_term::Style::new(),
                     })
                 }
             }
 
-            impl<'a> FmtRenderer<'a, str> for #random_id<'a> where Self: 'a + Clone
+            impl<'a> ::procr_ansi_term::FmtRenderer<'a, str> for #renderer_id<'a> where Self: 'a + Clone
             {
-                fn render_inputs_ref(&self) -> &[AnsiGenericString<'a, str>] {
+                fn render_inputs_ref(&self) -> &[::procr_ansi_term::AnsiGenericString<'a, str>] {
                     &self.render_inputs
                 }
 
-                fn render_inputs_mut(&mut self) -> &mut [AnsiGenericString<'a, str>] {
+                fn render_inputs_mut(&mut self) -> &mut [::procr_ansi_term::AnsiGenericString<'a, str>] {
                     &mut self.render_inputs
                 }
 
-                fn clone_renderer(&self) -> Box<dyn FmtRenderer<'a, str>> {
-                    Box::new(self.clone())
+                fn clone_renderer(&self) -> ::std::boxed::Box<dyn ::procr_ansi_term::FmtRenderer<'a, str>> {
+                    ::std::boxed::Box::new(self.clone())
+                }
+
+                fn context(&self) -> ::procr_ansi_term::Style {
+                    self.context
                 }
 
-                fn render(&self) -> String {
+                fn set_context(&mut self, context: ::procr_ansi_term::Style) {
+                    self.context = context;
+                }
+
+                fn render_strings(&self) -> ::procr_ansi_term::AnsiGenericStrings<'a, str> {
                     let render_inputs = self.render_inputs_ref();
-                    #format_args
+                    // The pieces are written as one sequence, so that they are
+                    // styled just like nested strings.
+                    let mut rendered = ::procr_ansi_term::AnsiGenericStrings::with_capacity(#segment_count);
+                    #(#segments)*
+                    rendered.rebase_on(self.context)
+                }
+
+                fn render_fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    ::std::write!(f, "{}", self.render_strings())
                 }
             }
 
-            impl<'a> Clone for #random_id<'a>  {
+            impl<'a> ::std::clone::Clone for #renderer_id<'a>  {
                 fn clone(&self) -> Self {
                     Self {
                         render_inputs: self.render_inputs.clone(),
+                        context: self.context,
                     }
                 }
             }
 
-            impl<'a> fmt::Debug for #random_id<'a>
+            impl<'a> ::std::fmt::Debug for #renderer_id<'a>
             {
-                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                    f.debug_struct(#random_id_str)
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    f.debug_struct(#renderer_id_str)
                         .field("render_inputs", &self.render_inputs)
-                        .field(
-                            "fmt_args", &#fmt_args_str
-                        ).finish()
+                        .field("context", &self.context)
+                        .field("fmt_s", &#fmt_s)
+                        .finish()
                 }
             }
 
-            #random_id::new_boxed(vec![#string_exprs])
+            #renderer_id::new_boxed(::std::vec![#string_exprs])
         }
     }
-    .into()
+}
+
+// Function like proc_macros have signature (TokenStream) -> TokenStream.
+/// Like `format!`, but for ANSI strings: returns a boxed `FmtRenderer`
+/// holding its arguments, which can be nested inside other ANSI strings.
+///
+/// The format string can also give styles, which are resolved at compile
+/// time:
+///
+/// - `{red.bold:name}` styles the argument `name` (or `{red.bold:name:>8}`
+///   with a format spec), with its words separated by dots;
+/// - `[bold red]...[/]` styles everything in between, with its words separated
+///   by spaces. These can be nested, and `[[` is an escaped `[`. Brackets
+///   which are never closed by a `[/]`, as in `[INFO] {}`, are left as they are.
+///
+/// Style words are formats (`bold`, `dim`, `italic`, `underline`, `blink`,
+/// `reverse`, `hidden`, `strikethrough`), colors (`red`, `light_blue`, ...,
+/// `default`, a 256-color number such as `208`, or a hex color such as
+/// `#ff8700`), and background colors (`on_blue` or `on blue`). An argument's
+/// own style is rebased on the style given to it. Unknown style words are
+/// compile errors.
+///
+/// Width, precision and alignment (as in `{:>8}` or `{:.3}`) are measured in
+/// the columns and characters of an argument's visible text, and padding is
+/// in the style given to the argument.
+#[proc_macro]
+pub fn ansi_format(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_format(parse_macro_input!(input)).into()
+}
+
+/// Like `println!`, but with the format string and arguments of
+/// [`ansi_format!`]. The output is written straight to stdout, without being
+/// rendered into a `String` first.
+#[proc_macro]
+pub fn ansi_println(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let renderer = expand_format(parse_macro_input!(input));
+    quote!(::std::println!("{}", #renderer)).into()
+}
+
+/// Like `eprintln!`, but with the format string and arguments of
+/// [`ansi_format!`]. The output is written straight to stderr, without being
+/// rendered into a `String` first.
+#[proc_macro]
+pub fn ansi_eprintln(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let renderer = expand_format(parse_macro_input!(input));
+    quote!(::std::eprintln!("{}", #renderer)).into()
+}
+
+/// The destination and format arguments given to `ansi_write!`.
+struct WriteArgs {
+    dst: Expr,
+    args: InputArgs,
+}
+
+impl Parse for WriteArgs {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        let dst = input.parse()?;
+        input.parse::<Token![,]>()?;
+        Ok(WriteArgs {
+            dst,
+            args: input.parse()?,
+        })
+    }
+}
+
+/// Like `write!`, but with the format string and arguments of
+/// [`ansi_format!`], writing to a `&mut W` where `W: AnyWrite` (such as the
+/// writers given by `fmt_write!` or `io_write!`). Evaluates to the writer's
+/// `WriteResult`.
+#[proc_macro]
+pub fn ansi_write(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let WriteArgs { dst, args } = parse_macro_input!(input);
+    let renderer = expand_format(args);
+    quote!(::procr_ansi_term::AnyWrite::write_fmt(#dst, ::std::format_args!("{}", #renderer)))
+        .into()
+}
+
+/// Like [`ansi_write!`], with a newline appended.
+#[proc_macro]
+pub fn ansi_writeln(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let WriteArgs { dst, args } = parse_macro_input!(input);
+    let renderer = expand_format(args);
+    quote!(::procr_ansi_term::AnyWrite::write_fmt(#dst, ::std::format_args!("{}\n", #renderer)))
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deterministic_expansion() {
+        let expand = || {
+            let args: InputArgs =
+                syn::parse_str(r#""[bold]{}[/] {red:name} {1}", "a", Style::new().paint("b")"#)
+                    .unwrap();
+            expand_format(args).to_string()
+        };
+        assert_eq!(expand(), expand());
+    }
 }
@@ -2,7 +2,6 @@
 //! [rustc_parse_format](https://crates.io/crates/ra-ap-rustc_parse_format) that
 //! is used to identify positional arguments. Source code: https://github.com/rust-lang/rust/blob/master/compiler/rustc_parse_format/src/lib.rs
 
-use ra_ap_rustc_index as rustc_index;
 use ra_ap_rustc_lexer as rustc_lexer;
 pub use Alignment::*;
 pub use Count::*;
@@ -843,7 +842,7 @@ impl<'a> Parser<'a> {
 
 // Assert a reasonable size for `Piece`
 #[cfg(all(target_arch = "x86_64", target_pointer_width = "64"))]
-rustc_index::static_assert_size!(Piece<'_>, 16);
+const _: () = assert!(std::mem::size_of::<Piece<'_>>() == 16);
 
 /// https://github.com/rust-lang/rust/blob/master/compiler/rustc_parse_format/src/tests.rs
 #[cfg(test)]
@@ -0,0 +1,528 @@
+//! Style markup inside format strings, which is resolved into `Style` values
+//! at compile time.
+//!
+//! A style is a list of words: formats (`bold`, `italic`, ...), a foreground
+//! color (`red`, `light_blue`, a 256-color number such as `208`, or a hex
+//! color such as `#ff8700`), and a background color (`on_blue`, or `on blue`).
+//! It can be given for one argument, with its words separated by dots
+//! (`{red.bold:name}`), or for a stretch of the format string, with its words
+//! separated by spaces (`[bold red]...[/]`).
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, ToTokens};
+use syn::{Error, LitStr, Result as SynResult};
+
+/// Format words, and the `Style` methods which set them.
+const FORMATS: [(&str, &str); 9] = [
+    ("bold", "bold"),
+    ("dim", "dimmed"),
+    ("dimmed", "dimmed"),
+    ("italic", "italic"),
+    ("underline", "underline"),
+    ("blink", "blink"),
+    ("reverse", "reverse"),
+    ("hidden", "hidden"),
+    ("strikethrough", "strikethrough"),
+];
+
+/// Color words, and the `Color` variants they name.
+const COLORS: [(&str, &str); 19] = [
+    ("black", "Black"),
+    ("dark_gray", "DarkGray"),
+    ("red", "Red"),
+    ("light_red", "LightRed"),
+    ("green", "Green"),
+    ("light_green", "LightGreen"),
+    ("yellow", "Yellow"),
+    ("light_yellow", "LightYellow"),
+    ("blue", "Blue"),
+    ("light_blue", "LightBlue"),
+    ("purple", "Purple"),
+    ("light_purple", "LightPurple"),
+    ("magenta", "Magenta"),
+    ("light_magenta", "LightMagenta"),
+    ("cyan", "Cyan"),
+    ("light_cyan", "LightCyan"),
+    ("white", "White"),
+    ("light_gray", "LightGray"),
+    ("default", "Default"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorSpec {
+    Named(&'static str),
+    Fixed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl ColorSpec {
+    fn parse(word: &str) -> Option<Self> {
+        if let Some(hex) = word.strip_prefix('#') {
+            if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            let channel = |ix: usize| u8::from_str_radix(&hex[ix..ix + 2], 16).ok();
+            return Some(ColorSpec::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        if word.bytes().all(|b| b.is_ascii_digit()) {
+            return word.parse().ok().map(ColorSpec::Fixed);
+        }
+        COLORS
+            .iter()
+            .find(|(name, _)| *name == word)
+            .map(|(_, variant)| ColorSpec::Named(variant))
+    }
+}
+
+impl ToTokens for ColorSpec {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.extend(match *self {
+            ColorSpec::Named(variant) => {
+                let variant = format_ident!("{}", variant);
+                quote!(::procr_ansi_term::Color::#variant)
+            }
+            ColorSpec::Fixed(n) => quote!(::procr_ansi_term::Color::Fixed(#n)),
+            ColorSpec::Rgb(r, g, b) => quote!(::procr_ansi_term::Color::Rgb(#r, #g, #b)),
+        })
+    }
+}
+
+/// A style given in a format string.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StyleSpec {
+    /// The `Style` methods setting each format, in `FORMATS` order.
+    formats: Vec<&'static str>,
+    fg: Option<ColorSpec>,
+    bg: Option<ColorSpec>,
+}
+
+impl StyleSpec {
+    pub fn is_empty(&self) -> bool {
+        self.formats.is_empty() && self.fg.is_none() && self.bg.is_none()
+    }
+
+    /// This style, with anything it leaves unset taken from `base`.
+    pub fn rebase_on(&self, base: &StyleSpec) -> StyleSpec {
+        let mut formats: Vec<&'static str> =
+            base.formats.iter().chain(&self.formats).copied().collect();
+        formats.sort_by_key(|format| FORMATS.iter().position(|(_, method)| method == format));
+        formats.dedup();
+        StyleSpec {
+            formats,
+            fg: self.fg.or(base.fg),
+            bg: self.bg.or(base.bg),
+        }
+    }
+
+    /// Parse a list of style words, adding an error for each unknown one.
+    fn parse<'s>(
+        lit: &LitStr,
+        mut words: impl Iterator<Item = (Range<usize>, &'s str)>,
+        errors: &mut Option<Error>,
+    ) -> StyleSpec {
+        let mut spec = StyleSpec::default();
+        while let Some((range, word)) = words.next() {
+            if let Some((_, method)) = FORMATS.iter().find(|(name, _)| *name == word) {
+                spec.formats.push(method);
+                continue;
+            }
+
+            let (is_bg, color_range, color) = if word == "on" {
+                match words.next() {
+                    Some((range, color)) => (true, range, color),
+                    None => {
+                        push_error(errors, lit, range, "expected a color after `on`");
+                        continue;
+                    }
+                }
+            } else if let Some(color) = word.strip_prefix("on_") {
+                (true, range, color)
+            } else {
+                (false, range, word)
+            };
+            match ColorSpec::parse(color) {
+                Some(color) if is_bg => spec.bg = Some(color),
+                Some(color) => spec.fg = Some(color),
+                None => {
+                    let word = &lit.value()[color_range.clone()];
+                    push_error(
+                        errors,
+                        lit,
+                        color_range,
+                        &format!("unknown style word `{}`", word),
+                    );
+                }
+            }
+        }
+        spec.rebase_on(&StyleSpec::default())
+    }
+}
+
+impl ToTokens for StyleSpec {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let formats = self
+            .formats
+            .iter()
+            .map(|method| format_ident!("{}", method));
+        let fg = self.fg.iter();
+        let bg = self.bg.iter();
+        tokens.extend(quote!(
+            ::procr_ansi_term::Style::new() #(.#formats())* #(.fg(#fg))* #(.bg(#bg))*
+        ))
+    }
+}
+
+/// The span of the given byte range of a string literal's value. This is the
+/// span of the whole literal where the range can't be pointed at, because the
+/// literal has escapes, or the compiler does not support it.
+fn subspan(lit: &LitStr, range: Range<usize>) -> Span {
+    let source = lit.token().to_string();
+    let value = lit.value();
+    source
+        .find('"')
+        .map(|quote| quote + 1)
+        .filter(|start| source.get(*start..start + value.len()) == Some(value.as_str()))
+        .and_then(|start| lit.token().subspan(start + range.start..start + range.end))
+        .unwrap_or_else(|| lit.span())
+}
+
+fn push_error(errors: &mut Option<Error>, lit: &LitStr, range: Range<usize>, message: &str) {
+    let error = Error::new(subspan(lit, range), message);
+    match errors {
+        Some(errors) => errors.combine(error),
+        None => *errors = Some(error),
+    }
+}
+
+/// The words in `s`, separated by `sep`, with their ranges (offset by `base`).
+fn words(s: &str, base: usize, sep: char) -> impl Iterator<Item = (Range<usize>, &str)> {
+    s.split(sep)
+        .scan(base, move |start, word| {
+            let range = *start..*start + word.len();
+            *start = range.end + sep.len_utf8();
+            Some((range, word))
+        })
+        .filter(|(_, word)| !word.is_empty())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '#'
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    let starts_identifier = match chars.next() {
+        Some('_') => !chars.as_str().is_empty(),
+        Some(c) => c.is_alphabetic(),
+        None => false,
+    };
+    starts_identifier && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Whether the text in square brackets is a markup tag: words separated by
+/// spaces.
+fn is_tag(tag: &str) -> bool {
+    tag.starts_with(is_word_char) && tag.chars().all(|c| c == ' ' || is_word_char(c))
+}
+
+/// The offsets of the `[...]` tags in `s` which are closed by a later `[/]`,
+/// scanning `s` the same way as [`Markup::parse`].
+fn closed_tags(s: &str) -> HashSet<usize> {
+    let mut closed = HashSet::new();
+    let mut open = Vec::new();
+    let mut ix = 0;
+    while let Some(start) = s[ix..].find(['{', '[']).map(|offset| ix + offset) {
+        let rest = &s[start..];
+        let c = if rest.starts_with('{') { '{' } else { '[' };
+        ix = start + 1;
+        if rest[1..].starts_with(c) {
+            ix += 1;
+        } else if c == '{' {
+            match rest.find('}') {
+                Some(end) => ix = start + end + 1,
+                None => break,
+            }
+        } else if let Some(end) = rest.find(']') {
+            match &rest[1..end] {
+                "/" => closed.extend(open.pop()),
+                tag if is_tag(tag) => open.push(start),
+                _ => continue,
+            }
+            ix = start + end + 1;
+        }
+    }
+    closed
+}
+
+/// An argument (`{...}`) in a format string.
+pub struct ArgMarkup {
+    /// Where the argument starts in the format string without markup.
+    pub offset: usize,
+    /// The span of the argument in the format string literal.
+    pub span: Span,
+    /// The style given with `{style:name}`.
+    pub style: StyleSpec,
+    /// The format spec of the argument (as in `{name:spec}`).
+    pub spec: String,
+}
+
+/// A format string, with its style markup taken out.
+pub struct Markup {
+    /// The format string without markup, as `format!` would take it.
+    pub fmt_s: String,
+    /// Offsets in `fmt_s` where the style given by `[...]` tags changes, and
+    /// the style from there on.
+    regions: Vec<(usize, StyleSpec)>,
+    /// The arguments in `fmt_s`, in order.
+    pub args: Vec<ArgMarkup>,
+}
+
+impl Markup {
+    /// Take the style markup out of the given format string, with an error
+    /// pointing at each unknown style word or unmatched `[/]`.
+    ///
+    /// Markup tags are words separated by spaces in square brackets, and are
+    /// closed by `[/]`. Tags which are never closed (such as `[INFO]`) and
+    /// anything else in square brackets are left as they are, and `[[` is an
+    /// escaped `[`.
+    pub fn parse(lit: &LitStr) -> SynResult<Markup> {
+        let s = lit.value();
+        let mut markup = Markup {
+            fmt_s: String::with_capacity(s.len()),
+            regions: vec![(0, StyleSpec::default())],
+            args: Vec::new(),
+        };
+        let closed = closed_tags(&s);
+        let mut stack: Vec<StyleSpec> = Vec::new();
+        let mut errors = None;
+
+        let mut chars = s.char_indices().peekable();
+        while let Some((ix, c)) = chars.next() {
+            let rest = &s[ix..];
+            let skip_to = match c {
+                '{' | '}' | '[' if rest[1..].starts_with(c) => {
+                    let escaped = if c == '[' { "[" } else { &rest[..2] };
+                    markup.fmt_s.push_str(escaped);
+                    ix + 1
+                }
+                '{' => {
+                    // Leave an unclosed argument for the format string parser
+                    // to report.
+                    let Some(end) = rest.find('}') else {
+                        markup.fmt_s.push_str(rest);
+                        break;
+                    };
+                    let inner = &rest[1..end];
+                    let offset = markup.fmt_s.len();
+                    let (style, name, spec) = match inner.split_once(':') {
+                        Some((style, arg))
+                            if !style.is_empty()
+                                && style.split('.').all(|word| {
+                                    !word.is_empty() && word.chars().all(is_word_char)
+                                })
+                                && is_identifier(arg.split(':').next().unwrap_or(arg)) =>
+                        {
+                            let style =
+                                StyleSpec::parse(lit, words(style, ix + 1, '.'), &mut errors);
+                            let (name, spec) = arg.split_once(':').unwrap_or((arg, ""));
+                            (style, name, spec)
+                        }
+                        Some((name, spec)) => (StyleSpec::default(), name, spec),
+                        None => (StyleSpec::default(), inner, ""),
+                    };
+                    markup.fmt_s.push('{');
+                    markup.fmt_s.push_str(name);
+                    if !spec.is_empty() {
+                        markup.fmt_s.push(':');
+                        markup.fmt_s.push_str(spec);
+                    }
+                    markup.fmt_s.push('}');
+                    markup.args.push(ArgMarkup {
+                        offset,
+                        span: subspan(lit, ix..ix + end + 1),
+                        style,
+                        spec: spec.to_string(),
+                    });
+                    ix + end
+                }
+                '[' => match rest.find(']').map(|end| (end, &rest[1..end])) {
+                    Some((end, "/")) => {
+                        if stack.pop().is_none() {
+                            push_error(
+                                &mut errors,
+                                lit,
+                                ix..ix + end + 1,
+                                "`[/]` does not close any style",
+                            );
+                        }
+                        let style = stack.last().cloned().unwrap_or_default();
+                        markup.regions.push((markup.fmt_s.len(), style));
+                        ix + end
+                    }
+                    Some((end, tag)) if is_tag(tag) && closed.contains(&ix) => {
+                        let style = StyleSpec::parse(lit, words(tag, ix + 1, ' '), &mut errors)
+                            .rebase_on(stack.last().unwrap_or(&StyleSpec::default()));
+                        stack.push(style.clone());
+                        markup.regions.push((markup.fmt_s.len(), style));
+                        ix + end
+                    }
+                    _ => {
+                        markup.fmt_s.push(c);
+                        ix
+                    }
+                },
+                c => {
+                    markup.fmt_s.push(c);
+                    ix
+                }
+            };
+            while chars.next_if(|(next, _)| *next <= skip_to).is_some() {}
+        }
+
+        match errors {
+            Some(errors) => Err(errors),
+            None => Ok(markup),
+        }
+    }
+
+    /// Split the given range of `fmt_s` where the style given by tags changes.
+    pub fn styles_in(
+        &self,
+        range: Range<usize>,
+    ) -> impl Iterator<Item = (Range<usize>, &StyleSpec)> + '_ {
+        self.regions
+            .iter()
+            .enumerate()
+            .filter_map(move |(ix, (start, style))| {
+                let end = self
+                    .regions
+                    .get(ix + 1)
+                    .map_or(usize::MAX, |(next, _)| *next);
+                let from = range.start.max(*start);
+                let to = range.end.min(end);
+                (from < to).then_some((from..to, style))
+            })
+    }
+
+    /// The style given by tags at the given offset in `fmt_s`.
+    pub fn style_at(&self, offset: usize) -> &StyleSpec {
+        self.regions
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= offset)
+            .map(|(_, style)| style)
+            .unwrap_or(&self.regions[0].1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s: &str) -> SynResult<Markup> {
+        Markup::parse(&LitStr::new(s, Span::call_site()))
+    }
+
+    fn spec(s: &str) -> StyleSpec {
+        let lit = LitStr::new(s, Span::call_site());
+        let mut errors = None;
+        let spec = StyleSpec::parse(&lit, words(s, 0, ' '), &mut errors);
+        assert!(errors.is_none(), "{} has unknown words", s);
+        spec
+    }
+
+    #[test]
+    fn style_words() {
+        assert_eq!(
+            spec("bold red on blue"),
+            StyleSpec {
+                formats: vec!["bold"],
+                fg: Some(ColorSpec::Named("Red")),
+                bg: Some(ColorSpec::Named("Blue")),
+            }
+        );
+        assert_eq!(
+            spec("italic 208 on_#00ff7f dim"),
+            StyleSpec {
+                formats: vec!["dimmed", "italic"],
+                fg: Some(ColorSpec::Fixed(208)),
+                bg: Some(ColorSpec::Rgb(0, 255, 127)),
+            }
+        );
+        assert_eq!(
+            spec("underline").rebase_on(&spec("bold green underline")),
+            spec("bold underline green")
+        );
+    }
+
+    #[test]
+    fn markup() {
+        let markup = parse("[bold]a [red]{}{{[/][/] {green.italic:x:>4} {y:?}[[c]").unwrap();
+        assert_eq!(markup.fmt_s, "a {}{{ {x:>4} {y:?}[c]");
+
+        let styles: Vec<_> = markup
+            .styles_in(0..markup.fmt_s.len())
+            .map(|(range, style)| (&markup.fmt_s[range], style.clone()))
+            .collect();
+        assert_eq!(
+            styles,
+            [
+                ("a ", spec("bold")),
+                ("{}{{", spec("bold red")),
+                (" {x:>4} {y:?}[c]", spec("")),
+            ]
+        );
+
+        let args: Vec<_> = markup
+            .args
+            .iter()
+            .map(|arg| (arg.offset, arg.style.clone(), arg.spec.as_str()))
+            .collect();
+        assert_eq!(
+            args,
+            [
+                (2, spec(""), ""),
+                (7, spec("green italic"), ">4"),
+                (14, spec(""), "?"),
+            ]
+        );
+        assert_eq!(markup.style_at(2), &spec("bold red"));
+    }
+
+    #[test]
+    fn not_markup() {
+        for s in [
+            "[1/3] {}",
+            "[] [ x] [{}]",
+            "{0} {:x} {a::>3}",
+            "[unclosed",
+            "[42] {}",
+            "[INFO] {}",
+        ] {
+            let markup = parse(s).unwrap();
+            assert_eq!(markup.fmt_s, s);
+            assert!(markup
+                .styles_in(0..s.len())
+                .all(|(_, style)| style.is_empty()));
+        }
+    }
+
+    #[test]
+    fn unknown_words() {
+        let errors = parse("[bold redd]{blu.bold:x}[/][/]").err().unwrap();
+        let messages: Vec<String> = errors.into_iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "unknown style word `redd`",
+                "unknown style word `blu`",
+                "`[/]` does not close any style",
+            ]
+        );
+        assert!(parse("[on]x[/]").is_err());
+        assert!(parse("{on_256:x}").is_err());
+    }
+}
@@ -1,22 +1,32 @@
 #![allow(missing_docs)]
+use crate::color_depth::ColorDepth;
 use crate::difference::StyleDelta;
-use crate::style::{Color, FormatFlags, Style};
+use crate::gradient::{ANSIColorCode, TargetGround};
+use crate::style::{Color, FormatFlags, Style, UnderlineKind};
 use crate::write::{AnyWrite, StrLike, WriteResult};
 use crate::{fmt_write, write_fmt, write_str};
 use std::fmt;
 
 impl FormatFlags {
     #[cfg(not(feature = "gnu_legacy"))]
-    fn as_format_char(self) -> Option<char> {
+    fn as_format_char(self) -> Option<&'static str> {
         match self {
-            FormatFlags::BOLD => '1'.into(),
-            FormatFlags::DIMMED => '2'.into(),
-            FormatFlags::ITALIC => '3'.into(),
-            FormatFlags::UNDERLINE => '4'.into(),
-            FormatFlags::BLINK => '5'.into(),
-            FormatFlags::REVERSE => '7'.into(),
-            FormatFlags::HIDDEN => '8'.into(),
-            FormatFlags::STRIKETHROUGH => '9'.into(),
+            FormatFlags::BOLD => "1".into(),
+            FormatFlags::DIMMED => "2".into(),
+            FormatFlags::ITALIC => "3".into(),
+            FormatFlags::UNDERLINE => "4".into(),
+            FormatFlags::BLINK => "5".into(),
+            FormatFlags::REVERSE => "7".into(),
+            FormatFlags::HIDDEN => "8".into(),
+            FormatFlags::STRIKETHROUGH => "9".into(),
+            FormatFlags::OVERLINE => "53".into(),
+            FormatFlags::RAPID_BLINK => "6".into(),
+            FormatFlags::FRAKTUR => "20".into(),
+            FormatFlags::FRAMED => "51".into(),
+            FormatFlags::ENCIRCLED => "52".into(),
+            FormatFlags::SUPERSCRIPT => "73".into(),
+            FormatFlags::SUBSCRIPT => "74".into(),
+            FormatFlags::DOUBLY_UNDERLINED => "21".into(),
             _ => None,
         }
     }
@@ -32,70 +42,164 @@ impl FormatFlags {
             FormatFlags::REVERSE => "07".into(),
             FormatFlags::HIDDEN => "08".into(),
             FormatFlags::STRIKETHROUGH => "09".into(),
+            FormatFlags::OVERLINE => "53".into(),
+            FormatFlags::RAPID_BLINK => "06".into(),
+            FormatFlags::FRAKTUR => "20".into(),
+            FormatFlags::FRAMED => "51".into(),
+            FormatFlags::ENCIRCLED => "52".into(),
+            FormatFlags::SUPERSCRIPT => "73".into(),
+            FormatFlags::SUBSCRIPT => "74".into(),
+            FormatFlags::DOUBLY_UNDERLINED => "21".into(),
             _ => None,
         }
     }
 }
 
+impl FormatFlags {
+    /// The SGR code which turns this format off again. Some formats share
+    /// the same code (see [`FormatFlags::sharing_off_code`]).
+    fn as_off_code(self) -> Option<&'static str> {
+        match self {
+            FormatFlags::BOLD | FormatFlags::DIMMED => "22".into(),
+            FormatFlags::ITALIC | FormatFlags::FRAKTUR => "23".into(),
+            FormatFlags::UNDERLINE | FormatFlags::DOUBLY_UNDERLINED => "24".into(),
+            FormatFlags::BLINK | FormatFlags::RAPID_BLINK => "25".into(),
+            FormatFlags::REVERSE => "27".into(),
+            FormatFlags::HIDDEN => "28".into(),
+            FormatFlags::STRIKETHROUGH => "29".into(),
+            FormatFlags::FRAMED | FormatFlags::ENCIRCLED => "54".into(),
+            FormatFlags::OVERLINE => "55".into(),
+            FormatFlags::SUPERSCRIPT | FormatFlags::SUBSCRIPT => "75".into(),
+            _ => None,
+        }
+    }
+
+    /// All the formats which are turned off by the same codes as those in
+    /// `self` (including `self`).
+    pub(crate) fn sharing_off_code(self) -> FormatFlags {
+        const SHARED: [FormatFlags; 6] = [
+            FormatFlags::BOLD.union(FormatFlags::DIMMED),
+            FormatFlags::ITALIC.union(FormatFlags::FRAKTUR),
+            FormatFlags::UNDERLINE.union(FormatFlags::DOUBLY_UNDERLINED),
+            FormatFlags::BLINK.union(FormatFlags::RAPID_BLINK),
+            FormatFlags::FRAMED.union(FormatFlags::ENCIRCLED),
+            FormatFlags::SUPERSCRIPT.union(FormatFlags::SUBSCRIPT),
+        ];
+
+        SHARED
+            .into_iter()
+            .filter(|shared| shared.intersects(self))
+            .fold(self, |all, shared| all | shared)
+    }
+}
+
+fn write_front<W: AnyWrite + ?Sized>(f: &mut W, write_occurred: bool) -> WriteResult<W::Error>
+where
+    str: AsRef<W::Buf>,
+    W::Buf: ToOwned,
+{
+    if write_occurred {
+        write_str!(f, ";")
+    } else {
+        // front of the ANSI escape code sequence
+        write_str!(f, "\x1B[")
+    }
+}
+
+fn write_code<W: AnyWrite + ?Sized, T, F: Fn(&mut W, T) -> WriteResult<W::Error>>(
+    f: &mut W,
+    input: Option<T>,
+    write_op: F,
+    write_occurred: bool,
+) -> Result<bool, W::Error>
+where
+    str: AsRef<W::Buf>,
+    W::Buf: ToOwned,
+{
+    if let Some(x) = input {
+        write_front(f, write_occurred)?;
+        write_op(f, x)?;
+        Ok(true)
+    } else {
+        Ok(write_occurred)
+    }
+}
+
 impl Style {
     /// Write any codes that go *before* a piece of text to the given writer.
+    ///
+    /// Colors are written as allowed by the global [`ColorDepth`].
     pub fn write_prefix<W: AnyWrite + ?Sized>(&self, f: &mut W) -> WriteResult<W::Error>
     where
         str: AsRef<W::Buf>,
         W::Buf: ToOwned,
     {
+        self.write_prefix_with_depth(f, ColorDepth::global())
+    }
+
+    /// Write any codes that go *before* a piece of text to the given writer,
+    /// with colors downgraded to the given `depth`.
+    pub fn write_prefix_with_depth<W: AnyWrite + ?Sized>(
+        &self,
+        f: &mut W,
+        depth: ColorDepth,
+    ) -> WriteResult<W::Error>
+    where
+        str: AsRef<W::Buf>,
+        W::Buf: ToOwned,
+    {
+        let style = self.downgrade(depth);
+
         // If there are actually no styles here, then don’t write *any* codes
         // as the prefix. An empty ANSI code may not affect the terminal
         // output at all, but a user may just want a code-free string.
-        if self.is_empty() {
+        if style.is_empty() {
             return Ok(());
         }
 
         // Prefix everything with reset characters if needed
-        if self.reset_prefix {
+        if style.reset_prefix {
             write_str!(f, "\x1B[0m")?
         }
 
-        if self.has_no_styling() {
+        if style.has_no_styling() {
             return Ok(());
         }
 
-        fn write_front<W: AnyWrite + ?Sized>(
-            f: &mut W,
-            write_occurred: bool,
-        ) -> WriteResult<W::Error>
-        where
-            str: AsRef<W::Buf>,
-            W::Buf: ToOwned,
-        {
-            if write_occurred {
-                write_str!(f, ";")
-            } else {
-                // front of the ANSI escape code sequence
-                write_str!(f, "\x1B[")
-            }
-        }
+        self.write_codes(FormatFlags::empty(), depth, f)
+    }
+
+    /// Write a single SGR sequence which first turns off the formats in `off`,
+    /// and then applies this style's formats and colors, downgraded to the
+    /// given `depth`. Every SGR code written for a style goes through here.
+    fn write_codes<W: AnyWrite + ?Sized>(
+        &self,
+        off: FormatFlags,
+        depth: ColorDepth,
+        f: &mut W,
+    ) -> WriteResult<W::Error>
+    where
+        str: AsRef<W::Buf>,
+        W::Buf: ToOwned,
+    {
+        let mut write_occurred = false;
 
-        fn write_code<W: AnyWrite + ?Sized, T, F: Fn(&mut W, T) -> WriteResult<W::Error>>(
-            f: &mut W,
-            input: Option<T>,
-            write_op: F,
-            write_occurred: bool,
-        ) -> Result<bool, W::Error>
-        where
-            str: AsRef<W::Buf>,
-            W::Buf: ToOwned,
-        {
-            if let Some(x) = input {
-                write_front(f, write_occurred)?;
-                write_op(f, x)?;
-                Ok(true)
-            } else {
-                Ok(write_occurred)
+        // Some formats are turned off by the same code, which only needs to
+        // be written once.
+        let mut off_written = FormatFlags::empty();
+        for (_, flag) in off.iter_names() {
+            if off_written.contains(flag) {
+                continue;
             }
+            off_written |= flag.sharing_off_code();
+            write_occurred = write_code(
+                f,
+                flag.as_off_code(),
+                |f, x| write_str!(f, x),
+                write_occurred,
+            )?;
         }
 
-        let mut write_occurred = false;
         for (_, flag) in self.formats.iter_names() {
             write_occurred = write_code(
                 f,
@@ -103,25 +207,39 @@ impl Style {
                 |f, x| write_fmt!(f, "{}", x),
                 write_occurred,
             )?;
+            // Kinds of underline other than the plain one are selected with
+            // a sub-parameter.
+            if flag == FormatFlags::UNDERLINE && self.underline_kind != UnderlineKind::Single {
+                write_fmt!(f, ":{}", self.underline_kind.code())?;
+            }
         }
 
         // The foreground and background colors, if specified, need to be
         // handled specially because the number codes are more complicated.
-        // (see `write_background_code` and `write_foreground_code`)
+        // (see `write_background_code` and `write_foreground_code`) Colors
+        // which cannot be shown at this depth are left out.
+        let downgrade = |color: Option<Color>| color.and_then(|c| c.downgrade(depth));
         write_occurred = write_code(
             f,
-            self.is_bg(),
+            downgrade(self.is_bg()),
             |f, x| x.write_background_code(f),
             write_occurred,
         )?;
 
         write_occurred = write_code(
             f,
-            self.is_fg(),
+            downgrade(self.is_fg()),
             |f, x| x.write_foreground_code(f),
             write_occurred,
         )?;
 
+        write_occurred = write_code(
+            f,
+            downgrade(self.is_underline_color()),
+            |f, x| x.write_underline_code(f),
+            write_occurred,
+        )?;
+
         if write_occurred {
             // All the codes end with an `m`, because reasons.
             write_str!(f, "m")?;
@@ -132,7 +250,17 @@ impl Style {
 
     /// Write any bytes that go *after* a piece of text to the given writer.
     fn write_suffix<W: AnyWrite + ?Sized>(&self, f: &mut W) -> WriteResult<W::Error> {
-        if self.is_empty() {
+        self.write_suffix_with_depth(f, ColorDepth::global())
+    }
+
+    /// Write any bytes that go *after* a piece of text to the given writer,
+    /// when the prefix was written with colors downgraded to `depth`.
+    pub(crate) fn write_suffix_with_depth<W: AnyWrite + ?Sized>(
+        &self,
+        f: &mut W,
+        depth: ColorDepth,
+    ) -> WriteResult<W::Error> {
+        if self.downgrade(depth).is_empty() {
             Ok(())
         } else {
             write_fmt!(f, "{}", RESET)
@@ -140,9 +268,40 @@ impl Style {
     }
 }
 
+impl StyleDelta {
+    /// Write the codes required to apply this delta to the given writer.
+    pub fn write_prefix<W: AnyWrite + ?Sized>(&self, f: &mut W) -> WriteResult<W::Error>
+    where
+        str: AsRef<W::Buf>,
+        W::Buf: ToOwned,
+    {
+        self.write_prefix_with_depth(f, ColorDepth::global())
+    }
+
+    /// Write the codes required to apply this delta to the given writer, with
+    /// colors downgraded to the given `depth`.
+    pub fn write_prefix_with_depth<W: AnyWrite + ?Sized>(
+        &self,
+        f: &mut W,
+        depth: ColorDepth,
+    ) -> WriteResult<W::Error>
+    where
+        str: AsRef<W::Buf>,
+        W::Buf: ToOwned,
+    {
+        match self {
+            StyleDelta::ExtraStyles(style) => style.write_prefix_with_depth(f, depth),
+            StyleDelta::TurnOff { off, then } => then.write_codes(*off, depth, f),
+            StyleDelta::Empty => Ok(()),
+        }
+    }
+}
+
 /// The code to send to reset all styles and return to `Style::default()`.
 pub static RESET: &str = "\x1B[0m";
 
+// These write colors exactly as they are: `Style::write_codes` is what
+// downgrades them to the color depth being written.
 impl Color {
     fn write_foreground_code<W: AnyWrite + ?Sized>(&self, f: &mut W) -> WriteResult<W::Error>
     where
@@ -201,6 +360,53 @@ impl Color {
             Color::LightGray => write_str!(f, "107"),
         }
     }
+
+    /// Underline colors have no codes for the named colors, so those are
+    /// written as their palette entries.
+    fn write_underline_code<W: AnyWrite + ?Sized>(&self, f: &mut W) -> WriteResult<W::Error>
+    where
+        str: AsRef<W::Buf>,
+    {
+        match self {
+            Color::Black => write_str!(f, "58;5;0"),
+            Color::Red => write_str!(f, "58;5;1"),
+            Color::Green => write_str!(f, "58;5;2"),
+            Color::Yellow => write_str!(f, "58;5;3"),
+            Color::Blue => write_str!(f, "58;5;4"),
+            Color::Purple => write_str!(f, "58;5;5"),
+            Color::Magenta => write_str!(f, "58;5;5"),
+            Color::Cyan => write_str!(f, "58;5;6"),
+            Color::White => write_str!(f, "58;5;7"),
+            Color::Fixed(num) => write_fmt!(f, "58;5;{}", num),
+            Color::Rgb(r, g, b) => write_fmt!(f, "58;2;{};{};{}", r, g, b),
+            Color::Default => write_str!(f, "59"),
+            Color::DarkGray => write_str!(f, "58;5;8"),
+            Color::LightRed => write_str!(f, "58;5;9"),
+            Color::LightGreen => write_str!(f, "58;5;10"),
+            Color::LightYellow => write_str!(f, "58;5;11"),
+            Color::LightBlue => write_str!(f, "58;5;12"),
+            Color::LightPurple => write_str!(f, "58;5;13"),
+            Color::LightMagenta => write_str!(f, "58;5;13"),
+            Color::LightCyan => write_str!(f, "58;5;14"),
+            Color::LightGray => write_str!(f, "58;5;15"),
+        }
+    }
+}
+
+/// The code is for the color exactly as it is, whatever the global
+/// [`ColorDepth`]: downgrade the color first with [`Color::downgrade`] (as
+/// [`Gradient::build`](crate::Gradient::build) does) if needed.
+impl ANSIColorCode for Color {
+    fn ansi_color_code(&self, target: TargetGround) -> String {
+        let mut code = String::new();
+        let w = fmt_write!(&mut code);
+        match target {
+            TargetGround::Foreground => self.write_foreground_code(w),
+            TargetGround::Background => self.write_background_code(w),
+        }
+        .expect("writing to a String cannot fail");
+        code
+    }
 }
 
 /// Like `AnsiString`, but only displays the style prefix.
@@ -403,10 +609,7 @@ impl fmt::Display for Prefix {
 
 impl fmt::Display for Infix {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.0.compute_delta(self.1) {
-            StyleDelta::ExtraStyles(style) => style.write_prefix(fmt_write!(f)),
-            StyleDelta::Empty => Ok(()),
-        }
+        self.0.compute_delta(self.1).write_prefix(fmt_write!(f))
     }
 }
 
@@ -456,7 +659,6 @@ macro_rules! create_content_eq_tests {
 #[cfg(test)]
 #[cfg(not(feature = "gnu_legacy"))]
 mod test {
-    use crate::create_content_eq_tests;
     use crate::style::Color::*;
     use crate::style::Style;
     use crate::style_test;
@@ -501,13 +703,68 @@ mod test {
         [reverse: Style::new().reverse(), "hi", "\x1B[7mhi\x1B[0m"]
         [hidden: Style::new().hidden(), "hi", "\x1B[8mhi\x1B[0m"]
         [stricken: Style::new().strikethrough(), "hi", "\x1B[9mhi\x1B[0m"]
+        [overline: Style::new().overline(), "hi", "\x1B[53mhi\x1B[0m"]
+        [rapid_blink: Style::new().rapid_blink(), "hi", "\x1B[6mhi\x1B[0m"]
+        [fraktur: Style::new().fraktur(), "hi", "\x1B[20mhi\x1B[0m"]
+        [framed_encircled: Style::new().framed().encircled(), "hi", "\x1B[51;52mhi\x1B[0m"]
+        [superscript: Red.superscript(), "hi", "\x1B[73;31mhi\x1B[0m"]
+        [subscript: Style::new().subscript(), "hi", "\x1B[74mhi\x1B[0m"]
+        [doubly_underlined: Style::new().doubly_underlined(), "hi", "\x1B[21mhi\x1B[0m"]
         [lr_on_lr: LightRed.on_bg(LightRed), "hi", "\x1B[101;91mhi\x1B[0m"]
+        [ul_light_magenta: Red.underline().underline_color(LightMagenta), "hi", "\x1B[4;31;58;5;13mhi\x1B[0m"]
+        [ul_rgb: Style::new().underline().underline_color(Rgb(1,2,3)), "hi", "\x1B[4;58;2;1;2;3mhi\x1B[0m"]
+        [ul_default: Style::new().underline().underline_color(Default), "hi", "\x1B[4;59mhi\x1B[0m"]
         @str_cmp [reset_format: Style::new().dimmed().infix(Style::new()).to_string(), "\x1B[0m"]
         @str_cmp [reset_then_style: White.dimmed().infix(White.as_fg()).to_string(), "\x1B[0m\x1B[37m"]
         @str_cmp [color_then_format: White.as_fg().infix(White.bold()).to_string(), "\x1B[1m"]
         @str_cmp [color_change: White.as_fg().infix(Blue.as_fg()).to_string(), "\x1B[34m"]
         @str_cmp [no_change: Blue.bold().infix(Blue.bold()).to_string(), ""]
     );
+
+    #[test]
+    fn color_depth_paths() {
+        use crate::{fmt_write, ANSIColorCode, AnsiStrings, Capabilities, ColorDepth};
+        use crate::{DeltaStrategy, TargetGround};
+        use std::fmt;
+
+        let orange = Rgb(255, 128, 0);
+        let write = |strings: AnsiStrings, depth| {
+            let mut s = String::new();
+            let capabilities = Capabilities {
+                color_depth: depth,
+                ..Capabilities::all()
+            };
+            strings
+                .write_to_any_with(fmt_write!(&mut s), capabilities)
+                .unwrap();
+            s
+        };
+
+        // Whole styles, including underline colors.
+        let mut s = String::new();
+        orange
+            .on_bg(Rgb(0, 0, 255))
+            .underline()
+            .underline_color(orange)
+            .write_prefix_with_depth(fmt_write!(&mut s), ColorDepth::Ansi256)
+            .unwrap();
+        assert_eq!(s, "\x1B[4;48;5;21;38;5;208;58;5;208m");
+
+        // Deltas which turn formats off.
+        let strings = AnsiStrings([orange.bold().paint("a"), Rgb(0, 0, 255).paint("b")])
+            .with_delta_strategy(DeltaStrategy::TurnOff);
+        assert_eq!(
+            write(strings.clone(), ColorDepth::Ansi16),
+            "\x1B[1;31ma\x1B[22;34mb\x1B[0m"
+        );
+        assert_eq!(write(strings, ColorDepth::None), "\x1B[1ma\x1B[22mb");
+
+        // The codes of a single color are not downgraded.
+        assert_eq!(
+            orange.ansi_color_code(TargetGround::Foreground),
+            "38;2;255;128;0"
+        );
+    }
 }
 
 #[cfg(test)]
@@ -573,6 +830,8 @@ mod gnu_legacy_test {
         [reverse: Style::new().reverse(), "hi", "\x1B[07mhi\x1B[0m"]
         [hidden: Style::new().hidden(), "hi", "\x1B[08mhi\x1B[0m"]
         [stricken: Style::new().strikethrough(), "hi", "\x1B[09mhi\x1B[0m"]
+        [rapid_blink: Style::new().rapid_blink(), "hi", "\x1B[06mhi\x1B[0m"]
+        [overline: Style::new().overline().bold(), "hi", "\x1B[01;53mhi\x1B[0m"]
         [lr_on_lr: LightRed.on_bg(LightRed), "hi", "\x1B[101;91mhi\x1B[0m"]
     );
 }
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::ColorDepth;
+
+/// The sequence which ends an OSC ("operating system command") escape
+/// sequence, such as those setting the window title or opening a hyperlink.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum OscTerminator {
+    /// The string terminator, `ESC \`, as given by the standard.
+    #[default]
+    St,
+    /// The bell character, `BEL`. Some older terminals and tmux passthrough
+    /// only understand this one.
+    Bel,
+}
+
+static GLOBAL_OSC_TERMINATOR: AtomicU8 = AtomicU8::new(OscTerminator::St as u8);
+
+impl OscTerminator {
+    /// The terminator used when writing OSC sequences, unless capabilities
+    /// are explicitly given (see [`Capabilities::osc_terminator`]).
+    pub fn global() -> OscTerminator {
+        match GLOBAL_OSC_TERMINATOR.load(Ordering::Relaxed) {
+            0 => OscTerminator::St,
+            _ => OscTerminator::Bel,
+        }
+    }
+
+    /// Set the terminator used when writing OSC sequences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::{AnsiGenericString, OscTerminator};
+    ///
+    /// OscTerminator::set_global(OscTerminator::Bel);
+    /// assert_eq!(AnsiGenericString::title("hello").to_string(), "\x1B]2;hello\x07");
+    /// ```
+    pub fn set_global(terminator: OscTerminator) {
+        GLOBAL_OSC_TERMINATOR.store(terminator as u8, Ordering::Relaxed)
+    }
+
+    /// The characters making up this terminator.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            OscTerminator::St => "\x1B\\",
+            OscTerminator::Bel => "\x07",
+        }
+    }
+}
+
+/// What a terminal (or other output stream) is able to display.
+///
+/// Writers such as [`AnsiGenericStrings::write_to_any_with`](crate::AnsiGenericStrings::write_to_any_with)
+/// use a profile to decide which escape sequences to emit: colors are
+/// downgraded to `color_depth`, hyperlinks are written as plain text when not
+/// supported, and titles are left out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    /// The colors which can be displayed.
+    pub color_depth: ColorDepth,
+    /// Whether OSC 8 hyperlinks are supported.
+    pub hyperlinks: bool,
+    /// Whether the window title can be set.
+    pub titles: bool,
+    /// How OSC sequences (titles and hyperlinks) are ended.
+    pub osc_terminator: OscTerminator,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Whether a variable is set to something other than the empty string.
+fn is_set(env: &HashMap<String, String>, key: &str) -> bool {
+    env.get(key).is_some_and(|v| !v.is_empty())
+}
+
+impl Capabilities {
+    /// Everything is supported.
+    pub const fn all() -> Self {
+        Self {
+            color_depth: ColorDepth::TrueColor,
+            hyperlinks: true,
+            titles: true,
+            osc_terminator: OscTerminator::St,
+        }
+    }
+
+    /// No colors, hyperlinks or titles are supported. Formatting such as bold
+    /// is still written.
+    pub const fn none() -> Self {
+        Self {
+            color_depth: ColorDepth::None,
+            hyperlinks: false,
+            titles: false,
+            osc_terminator: OscTerminator::St,
+        }
+    }
+
+    /// The profile used by writers when none is given explicitly: everything
+    /// is supported, with colors limited to the global [`ColorDepth`] and OSC
+    /// sequences ended by the global [`OscTerminator`].
+    pub fn global() -> Self {
+        Self {
+            color_depth: ColorDepth::global(),
+            osc_terminator: OscTerminator::global(),
+            ..Self::all()
+        }
+    }
+
+    /// Detect the capabilities of the standard output stream, from the process
+    /// environment.
+    pub fn detect_stdout() -> Self {
+        Self::from_env(&std::env::vars().collect(), std::io::stdout().is_terminal())
+    }
+
+    /// Detect the capabilities of the standard error stream, from the process
+    /// environment.
+    pub fn detect_stderr() -> Self {
+        Self::from_env(&std::env::vars().collect(), std::io::stderr().is_terminal())
+    }
+
+    /// Work out the capabilities of a stream from the given environment
+    /// variables, and whether the stream is a terminal.
+    ///
+    /// Colors are decided by the following rules, in order:
+    /// - a non-empty `NO_COLOR` disables colors;
+    /// - a non-empty `CLICOLOR_FORCE` other than `0` enables colors, even when
+    ///   the stream is not a terminal;
+    /// - `CLICOLOR=0`, a stream which is not a terminal, or `TERM=dumb`
+    ///   disable colors.
+    ///
+    /// When enabled, `COLORTERM=truecolor` (or `24bit`) and a `TERM` ending in
+    /// `-direct` give 24-bit colors, a `TERM` containing `256color` gives the
+    /// 256-color palette, and anything else gives the 16 basic colors.
+    ///
+    /// Titles are supported on any terminal other than `TERM=dumb`. Hyperlinks
+    /// are only assumed for terminals known to support them, and can be
+    /// overridden with `FORCE_HYPERLINK=1` (or `0`). OSC sequences are ended by
+    /// the global [`OscTerminator`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use procr_ansi_term::{Capabilities, ColorDepth};
+    ///
+    /// let env = HashMap::from([("TERM".to_string(), "xterm-256color".to_string())]);
+    /// let capabilities = Capabilities::from_env(&env, true);
+    /// assert_eq!(capabilities.color_depth, ColorDepth::Ansi256);
+    /// assert!(capabilities.titles);
+    ///
+    /// assert_eq!(Capabilities::from_env(&env, false), Capabilities::none());
+    /// ```
+    pub fn from_env(env: &HashMap<String, String>, is_tty: bool) -> Self {
+        let term = env.get("TERM").map(String::as_str).unwrap_or_default();
+        let is_dumb = term == "dumb";
+        let usable_terminal = is_tty && !is_dumb;
+
+        let color_enabled = if is_set(env, "NO_COLOR") {
+            false
+        } else if is_set(env, "CLICOLOR_FORCE") && env["CLICOLOR_FORCE"] != "0" {
+            true
+        } else {
+            env.get("CLICOLOR").map(String::as_str) != Some("0") && usable_terminal
+        };
+
+        let color_depth = if !color_enabled {
+            ColorDepth::None
+        } else if matches!(
+            env.get("COLORTERM").map(String::as_str),
+            Some("truecolor" | "24bit")
+        ) || term.ends_with("-direct")
+        {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        };
+
+        let hyperlinks = match env.get("FORCE_HYPERLINK").map(String::as_str) {
+            Some("0") => false,
+            Some(v) if !v.is_empty() => true,
+            _ => usable_terminal && Self::known_hyperlink_terminal(env, term),
+        };
+
+        Self {
+            color_depth,
+            hyperlinks,
+            titles: usable_terminal,
+            osc_terminator: OscTerminator::global(),
+        }
+    }
+
+    fn known_hyperlink_terminal(env: &HashMap<String, String>, term: &str) -> bool {
+        let term_program = env.get("TERM_PROGRAM").map(String::as_str);
+        let vte_version = env
+            .get("VTE_VERSION")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or_default();
+
+        matches!(
+            term_program,
+            Some("iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper")
+        ) || vte_version >= 5000
+            || is_set(env, "WT_SESSION")
+            || is_set(env, "KONSOLE_VERSION")
+            || term.contains("kitty")
+            || term.contains("alacritty")
+            || term.starts_with("foot")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn depth(vars: &[(&str, &str)], is_tty: bool) -> ColorDepth {
+        Capabilities::from_env(&env(vars), is_tty).color_depth
+    }
+
+    #[test]
+    fn color_depths() {
+        assert_eq!(depth(&[], true), ColorDepth::Ansi16);
+        assert_eq!(depth(&[("TERM", "xterm")], true), ColorDepth::Ansi16);
+        assert_eq!(
+            depth(&[("TERM", "screen-256color")], true),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(
+            depth(&[("TERM", "xterm-direct")], true),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            depth(
+                &[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")],
+                true
+            ),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            depth(&[("COLORTERM", "24bit")], true),
+            ColorDepth::TrueColor
+        );
+    }
+
+    #[test]
+    fn color_switches() {
+        assert_eq!(depth(&[("TERM", "xterm")], false), ColorDepth::None);
+        assert_eq!(depth(&[("TERM", "dumb")], true), ColorDepth::None);
+        assert_eq!(depth(&[("NO_COLOR", "1")], true), ColorDepth::None);
+        assert_eq!(depth(&[("NO_COLOR", "")], true), ColorDepth::Ansi16);
+        assert_eq!(depth(&[("CLICOLOR", "0")], true), ColorDepth::None);
+        assert_eq!(depth(&[("CLICOLOR", "1")], false), ColorDepth::None);
+        assert_eq!(depth(&[("CLICOLOR_FORCE", "1")], false), ColorDepth::Ansi16);
+        assert_eq!(depth(&[("CLICOLOR_FORCE", "0")], false), ColorDepth::None);
+        assert_eq!(
+            depth(&[("CLICOLOR_FORCE", "1"), ("CLICOLOR", "0")], true),
+            ColorDepth::Ansi16
+        );
+        assert_eq!(
+            depth(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")], true),
+            ColorDepth::None
+        );
+    }
+
+    #[test]
+    fn hyperlinks_and_titles() {
+        let caps = |vars, is_tty| Capabilities::from_env(&env(vars), is_tty);
+
+        let plain = caps(&[("TERM", "xterm-256color")], true);
+        assert!(!plain.hyperlinks);
+        assert!(plain.titles);
+
+        assert!(caps(&[("TERM_PROGRAM", "WezTerm")], true).hyperlinks);
+        assert!(caps(&[("VTE_VERSION", "6003")], true).hyperlinks);
+        assert!(!caps(&[("VTE_VERSION", "4000")], true).hyperlinks);
+        assert!(caps(&[("TERM", "xterm-kitty")], true).hyperlinks);
+        assert!(!caps(&[("TERM", "xterm-kitty")], false).hyperlinks);
+        assert!(caps(&[("FORCE_HYPERLINK", "1")], false).hyperlinks);
+        assert!(!caps(&[("FORCE_HYPERLINK", "0"), ("WT_SESSION", "x")], true).hyperlinks);
+
+        // Hyperlinks and titles are not colors.
+        let no_color = caps(&[("NO_COLOR", "1"), ("TERM_PROGRAM", "iTerm.app")], true);
+        assert!(no_color.hyperlinks);
+        assert!(no_color.titles);
+
+        assert!(!caps(&[("TERM", "dumb")], true).titles);
+    }
+}
@@ -0,0 +1,388 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+use crate::{Color, Rgb, Style};
+
+/// The range of colors that a terminal is able to display.
+///
+/// Colors which cannot be displayed at a given depth are replaced by the
+/// (perceptually) nearest color which can, when styles are written out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ColorDepth {
+    /// 24-bit colors: every color is written as-is.
+    #[default]
+    TrueColor,
+    /// The 256-color palette: [`Color::Rgb`] is mapped to a [`Color::Fixed`]
+    /// color.
+    Ansi256,
+    /// The 16 basic colors: [`Color::Rgb`] and [`Color::Fixed`] are mapped to
+    /// one of the named colors.
+    Ansi16,
+    /// No colors are written at all (formatting such as bold is kept).
+    None,
+}
+
+static GLOBAL_COLOR_DEPTH: AtomicU8 = AtomicU8::new(ColorDepth::TrueColor as u8);
+
+impl ColorDepth {
+    /// The color depth used when writing styles, unless one is explicitly
+    /// given (for example with [`Style::write_prefix_with_depth`]).
+    pub fn global() -> ColorDepth {
+        match GLOBAL_COLOR_DEPTH.load(Ordering::Relaxed) {
+            0 => ColorDepth::TrueColor,
+            1 => ColorDepth::Ansi256,
+            2 => ColorDepth::Ansi16,
+            _ => ColorDepth::None,
+        }
+    }
+
+    /// Set the color depth used when writing styles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::{Color, ColorDepth};
+    ///
+    /// ColorDepth::set_global(ColorDepth::Ansi256);
+    /// assert_eq!(
+    ///     Color::Rgb(255, 128, 0).paint("orange").to_string(),
+    ///     "\x1B[38;5;208morange\x1B[0m"
+    /// );
+    /// ```
+    pub fn set_global(depth: ColorDepth) {
+        GLOBAL_COLOR_DEPTH.store(depth as u8, Ordering::Relaxed)
+    }
+}
+
+/// The xterm defaults for the 16 basic colors.
+const ANSI_16: [Rgb; 16] = [
+    Rgb::from_hex(0x000000),
+    Rgb::from_hex(0xcd0000),
+    Rgb::from_hex(0x00cd00),
+    Rgb::from_hex(0xcdcd00),
+    Rgb::from_hex(0x0000ee),
+    Rgb::from_hex(0xcd00cd),
+    Rgb::from_hex(0x00cdcd),
+    Rgb::from_hex(0xe5e5e5),
+    Rgb::from_hex(0x7f7f7f),
+    Rgb::from_hex(0xff0000),
+    Rgb::from_hex(0x00ff00),
+    Rgb::from_hex(0xffff00),
+    Rgb::from_hex(0x5c5cff),
+    Rgb::from_hex(0xff00ff),
+    Rgb::from_hex(0x00ffff),
+    Rgb::from_hex(0xffffff),
+];
+
+/// The named colors, in palette order.
+const NAMED: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Purple,
+    Color::Cyan,
+    Color::White,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightPurple,
+    Color::LightCyan,
+    Color::LightGray,
+];
+
+/// Channel values used by the 6x6x6 color cube (palette entries 16 to 231).
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The xterm default RGB value of a 256-color palette entry.
+const fn palette_rgb(index: u8) -> Rgb {
+    match index {
+        0..=15 => ANSI_16[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            Rgb::new(
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => Rgb::gray(8 + 10 * (index - 232)),
+    }
+}
+
+/// The RGB values that colors are shown as, for renderers which do not go
+/// through a terminal (such as [`HtmlRenderer`](crate::HtmlRenderer)).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    /// Entries 0 to 255 of the 256-color palette. The first 16 are the named
+    /// colors.
+    pub colors: [Rgb; 256],
+    /// The default foreground color.
+    pub foreground: Rgb,
+    /// The default background color.
+    pub background: Rgb,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::xterm()
+    }
+}
+
+impl Palette {
+    /// The xterm default palette, with light gray text on a black background.
+    pub const fn xterm() -> Self {
+        let mut colors = [Rgb::gray(0); 256];
+        let mut ix = 0;
+        while ix < 256 {
+            colors[ix] = palette_rgb(ix as u8);
+            ix += 1;
+        }
+        Self {
+            colors,
+            foreground: ANSI_16[7],
+            background: ANSI_16[0],
+        }
+    }
+
+    /// Change the RGB value of a palette color (a named color, or a
+    /// [`Color::Fixed`] one). Other colors are left as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::{Color, Palette, Rgb};
+    ///
+    /// let solarized_red = Rgb::from_hex(0xdc322f);
+    /// let palette = Palette::xterm().with_color(Color::Red, solarized_red);
+    /// assert_eq!(palette.rgb(Color::Fixed(1)), Some(solarized_red));
+    /// ```
+    pub fn with_color(mut self, color: Color, rgb: Rgb) -> Self {
+        if let Some(ix) = color.index() {
+            self.colors[ix as usize] = rgb;
+        }
+        self
+    }
+
+    /// The RGB value that the given color is shown as, or `None` for
+    /// [`Color::Default`].
+    pub fn rgb(&self, color: Color) -> Option<Rgb> {
+        match color {
+            Color::Rgb(r, g, b) => Some(Rgb::new(r, g, b)),
+            _ => color.index().map(|ix| self.colors[ix as usize]),
+        }
+    }
+}
+
+/// A color in the CIE L*a*b* color space, where Euclidean distance roughly
+/// corresponds to perceived difference.
+type Lab = [f32; 3];
+
+fn to_lab(rgb: Rgb) -> Lab {
+    fn linear(c: u8) -> f32 {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    fn f(t: f32) -> f32 {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    }
+
+    let (r, g, b) = (linear(rgb.r), linear(rgb.g), linear(rgb.b));
+    // sRGB to XYZ, relative to the D65 white point.
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn palette_lab() -> &'static [Lab; 256] {
+    static PALETTE_LAB: OnceLock<[Lab; 256]> = OnceLock::new();
+    PALETTE_LAB.get_or_init(|| {
+        let mut labs = [[0.0; 3]; 256];
+        for (ix, lab) in labs.iter_mut().enumerate() {
+            *lab = to_lab(palette_rgb(ix as u8));
+        }
+        labs
+    })
+}
+
+/// The palette entry within `candidates` which is perceptually closest to
+/// `rgb`.
+fn nearest(rgb: Rgb, candidates: std::ops::RangeInclusive<u8>) -> u8 {
+    let target = to_lab(rgb);
+    let labs = palette_lab();
+    candidates
+        .min_by(|&a, &b| {
+            let distance = |ix: u8| {
+                let lab = labs[ix as usize];
+                (0..3).map(|c| (lab[c] - target[c]).powi(2)).sum::<f32>()
+            };
+            distance(a).total_cmp(&distance(b))
+        })
+        .unwrap_or_default()
+}
+
+impl Color {
+    /// The position of this color in the 256-color palette, if it has one.
+    ///
+    /// Named colors are entries 0 to 15.
+    pub const fn index(self) -> Option<u8> {
+        match self {
+            Color::Black => Some(0),
+            Color::Red => Some(1),
+            Color::Green => Some(2),
+            Color::Yellow => Some(3),
+            Color::Blue => Some(4),
+            Color::Purple | Color::Magenta => Some(5),
+            Color::Cyan => Some(6),
+            Color::White => Some(7),
+            Color::DarkGray => Some(8),
+            Color::LightRed => Some(9),
+            Color::LightGreen => Some(10),
+            Color::LightYellow => Some(11),
+            Color::LightBlue => Some(12),
+            Color::LightPurple | Color::LightMagenta => Some(13),
+            Color::LightCyan => Some(14),
+            Color::LightGray => Some(15),
+            Color::Fixed(n) => Some(n),
+            Color::Rgb(..) | Color::Default => None,
+        }
+    }
+
+    /// The RGB value of this color. Palette colors use the xterm defaults,
+    /// although most terminals allow the first 16 to be customised.
+    /// [`Color::Default`] has no RGB value.
+    pub const fn to_rgb(self) -> Option<Rgb> {
+        match self {
+            Color::Rgb(r, g, b) => Some(Rgb::new(r, g, b)),
+            _ => match self.index() {
+                Some(ix) => Some(palette_rgb(ix)),
+                None => None,
+            },
+        }
+    }
+
+    /// Convert this color into one which can be displayed at the given
+    /// `depth`, using the nearest available color. Returns `None` if no color
+    /// should be displayed at all.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::{Color, ColorDepth};
+    ///
+    /// let orange = Color::Rgb(255, 128, 0);
+    /// assert_eq!(orange.downgrade(ColorDepth::TrueColor), Some(orange));
+    /// assert_eq!(orange.downgrade(ColorDepth::Ansi256), Some(Color::Fixed(208)));
+    /// assert_eq!(orange.downgrade(ColorDepth::Ansi16), Some(Color::Red));
+    /// assert_eq!(orange.downgrade(ColorDepth::None), None);
+    /// ```
+    pub fn downgrade(self, depth: ColorDepth) -> Option<Color> {
+        match (depth, self) {
+            (ColorDepth::None, _) => None,
+            (ColorDepth::TrueColor, _) => Some(self),
+            // Entries 0 to 15 are configurable, so only the fixed part of the
+            // palette can stand in for an exact color.
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => {
+                Some(Color::Fixed(nearest(Rgb::new(r, g, b), 16..=255)))
+            }
+            (ColorDepth::Ansi16, Color::Fixed(n)) if n < 16 => Some(NAMED[n as usize]),
+            (ColorDepth::Ansi16, Color::Fixed(_) | Color::Rgb(..)) => {
+                let rgb = self.to_rgb().unwrap_or(Rgb::gray(0));
+                Some(NAMED[nearest(rgb, 0..=15) as usize])
+            }
+            (ColorDepth::Ansi256 | ColorDepth::Ansi16, _) => Some(self),
+        }
+    }
+}
+
+impl Style {
+    /// Convert the colors of this style into ones which can be displayed at
+    /// the given `depth` (see [`Color::downgrade`]).
+    pub fn downgrade(self, depth: ColorDepth) -> Style {
+        let fg = self.coloring.fg.and_then(|c| c.downgrade(depth));
+        let bg = self.coloring.bg.and_then(|c| c.downgrade(depth));
+        let underline = self.coloring.underline.and_then(|c| c.downgrade(depth));
+        self.set_fg(fg).set_bg(bg).set_underline_color(underline)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Color::{Black, Default, Fixed, LightRed, LightYellow, Magenta, Purple, Red};
+
+    #[test]
+    fn palette() {
+        assert_eq!(palette_rgb(1), Rgb::new(205, 0, 0));
+        assert_eq!(palette_rgb(16), Rgb::new(0, 0, 0));
+        assert_eq!(palette_rgb(196), Rgb::new(255, 0, 0));
+        assert_eq!(palette_rgb(231), Rgb::new(255, 255, 255));
+        assert_eq!(palette_rgb(232), Rgb::gray(8));
+        assert_eq!(palette_rgb(255), Rgb::gray(238));
+        assert_eq!(Purple.to_rgb(), Magenta.to_rgb());
+        assert_eq!(Default.to_rgb(), None);
+    }
+
+    #[test]
+    fn exact_palette_colors_are_kept() {
+        for ix in 16..=255 {
+            let rgb = palette_rgb(ix);
+            assert_eq!(
+                Color::Rgb(rgb.r, rgb.g, rgb.b).downgrade(ColorDepth::Ansi256),
+                Some(Fixed(ix))
+            );
+        }
+        for (ix, color) in NAMED.into_iter().enumerate() {
+            assert_eq!(Fixed(ix as u8).downgrade(ColorDepth::Ansi16), Some(color));
+            assert_eq!(color.downgrade(ColorDepth::Ansi16), Some(color));
+        }
+    }
+
+    #[test]
+    fn nearest_colors() {
+        assert_eq!(
+            Color::Rgb(250, 5, 5).downgrade(ColorDepth::Ansi256),
+            Some(Fixed(196))
+        );
+        assert_eq!(
+            Color::Rgb(100, 100, 100).downgrade(ColorDepth::Ansi256),
+            Some(Fixed(241))
+        );
+        assert_eq!(
+            Color::Rgb(200, 10, 10).downgrade(ColorDepth::Ansi16),
+            Some(Red)
+        );
+        assert_eq!(
+            Color::Rgb(10, 10, 30).downgrade(ColorDepth::Ansi16),
+            Some(Black)
+        );
+        assert_eq!(Fixed(226).downgrade(ColorDepth::Ansi16), Some(LightYellow));
+        assert_eq!(Default.downgrade(ColorDepth::Ansi16), Some(Default));
+    }
+
+    #[test]
+    fn styles() {
+        let style = Color::Rgb(250, 5, 5).on_bg(Fixed(226)).bold();
+        assert_eq!(style.downgrade(ColorDepth::TrueColor), style);
+        assert_eq!(
+            style.downgrade(ColorDepth::Ansi16),
+            LightRed.on_bg(LightYellow).bold()
+        );
+        assert_eq!(style.downgrade(ColorDepth::None), Style::new().bold());
+    }
+}
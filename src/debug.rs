@@ -3,7 +3,7 @@ use itertools::Itertools;
 use crate::{
     difference::StyleDelta,
     fmt_write,
-    style::{Coloring, FormatFlags, Style},
+    style::{Coloring, FormatFlags, Style, UnderlineKind},
     write::Content,
     AnsiGenericString, Color, Infix,
 };
@@ -31,6 +31,7 @@ fn debug_write_coloring_to(
     f: &mut dyn fmt::Write,
     fg: Option<Color>,
     bg: Option<Color>,
+    underline: Option<Color>,
     mut sep_required: bool,
 ) -> Result<bool, fmt::Error> {
     if let Some(color) = fg {
@@ -45,6 +46,14 @@ fn debug_write_coloring_to(
             "{}background({color:?})",
             if sep_required { ", " } else { "" }
         ))?;
+        sep_required = true;
+    }
+    if let Some(color) = underline {
+        f.write_fmt(format_args!(
+            "{}underline_color({color:?})",
+            if sep_required { ", " } else { "" }
+        ))?;
+        sep_required = true;
     }
     Ok(sep_required)
 }
@@ -52,27 +61,38 @@ fn debug_write_coloring_to(
 fn debug_write_style_to(
     f: &mut dyn fmt::Write,
     flags: FormatFlags,
-    fg: Option<Color>,
-    bg: Option<Color>,
+    underline_kind: UnderlineKind,
+    coloring: Coloring,
 ) -> Result<(), fmt::Error> {
     f.write_str("Style { ")?;
 
     let mut sep_required = false;
     let mut flag_strings: Vec<String> = flags
         .iter_names()
-        .filter_map(|n| {
-            let name = n.0;
+        .filter_map(|(name, flag)| {
             if name == "FOREGROUND" || name == "BACKGROUND" {
                 None
             } else {
                 sep_required = true;
-                name.to_lowercase().into()
+                if flag == FormatFlags::UNDERLINE && underline_kind != UnderlineKind::Single {
+                    format!("underline({underline_kind:?})")
+                        .to_lowercase()
+                        .into()
+                } else {
+                    name.to_lowercase().into()
+                }
             }
         })
         .collect();
     flag_strings.sort_unstable();
     f.write_str(&flag_strings.join(", "))?;
-    debug_write_coloring_to(f, fg, bg, sep_required)?;
+    debug_write_coloring_to(
+        f,
+        coloring.fg,
+        coloring.bg,
+        coloring.underline,
+        sep_required,
+    )?;
     f.write_str(" }")
 }
 
@@ -83,7 +103,12 @@ fn debug_style_to_string(
 ) -> Result<String, fmt::Error> {
     let mut dbg_s = String::new();
     let f = fmt_write!(&mut dbg_s);
-    debug_write_style_to(f, flags, fg, bg)?;
+    let coloring = Coloring {
+        fg,
+        bg,
+        underline: None,
+    };
+    debug_write_style_to(f, flags, UnderlineKind::Single, coloring)?;
     Ok(dbg_s)
 }
 
@@ -98,6 +123,10 @@ fn debug_style_to_string(
 ///         "Style { bold, italic, foreground(Red), background(Blue) }",
 ///        format!("{:#?}", Red.on_bg(Blue).bold().italic())
 ///     );
+///     assert_eq!(
+///         "Style { underline(curly), foreground(Red) }",
+///        format!("{:#?}", Red.curly_underline())
+///     );
 /// ```
 impl Debug for Style {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -105,13 +134,10 @@ impl Debug for Style {
             f.debug_struct("Style")
                 .field("flags", &self.formats)
                 .field("coloring", &self.coloring)
+                .field("underline_kind", &self.underline_kind)
                 .finish()
         } else {
-            write!(
-                f,
-                "{}",
-                debug_style_to_string(self.formats, self.coloring.fg, self.coloring.bg)?
-            )
+            debug_write_style_to(f, self.formats, self.underline_kind, self.coloring)
         }
     }
 }
@@ -122,9 +148,10 @@ impl Debug for Coloring {
             f.debug_struct("Coloring")
                 .field("foreground", &self.fg)
                 .field("background", &self.bg)
+                .field("underline", &self.underline)
                 .finish()
         } else {
-            debug_write_coloring_to(f, self.fg, self.bg, false)?;
+            debug_write_coloring_to(f, self.fg, self.bg, self.underline, false)?;
             Ok(())
         }
     }
@@ -257,12 +284,9 @@ pub trait DebugStylePaint: Clone + Copy {
 
     /// Paints the given text with this style, returning an ANSI string.
     #[inline]
-    fn style_input<'a, I: ToOwned, S: 'a + ToOwned + ?Sized>(
-        &self,
-        input: I,
-    ) -> AnsiGenericString<'a, S>
+    fn style_input<'a, I, S: 'a + ToOwned + ?Sized>(&self, input: I) -> AnsiGenericString<'a, S>
     where
-        I: Into<Content<'a, S>>,
+        I: ToOwned + Into<Content<'a, S>>,
     {
         AnsiGenericString::new(self.into_style(), input.into(), None)
     }
@@ -301,7 +325,7 @@ impl DebugStylePaint for Color {
 impl DebugStylePaint for Infix {
     fn into_style(self) -> Style {
         match self.0.compute_delta(self.1) {
-            StyleDelta::ExtraStyles(style) => style,
+            StyleDelta::ExtraStyles(style) | StyleDelta::TurnOff { then: style, .. } => style,
             StyleDelta::Empty => Style::new(),
         }
     }
@@ -367,19 +391,32 @@ impl<T: DebugDiff> DebugDiff for Option<T> {
 impl DebugDiff for Coloring {
     fn debug_diff(&self, expected: &Self) -> String {
         format!(
-            "Coloring {{\n\tforeground: {},\n\tbackground: {},\n}}",
+            "Coloring {{\n\tforeground: {},\n\tbackground: {},\n\tunderline: {},\n}}",
             self.fg.debug_diff(&expected.fg),
-            expected.bg.debug_diff(&expected.bg)
+            self.bg.debug_diff(&expected.bg),
+            self.underline.debug_diff(&expected.underline),
         )
     }
 }
 
+impl DebugDiff for UnderlineKind {
+    fn debug_diff(&self, expected: &Self) -> String {
+        if self != expected {
+            format!("UnderlineKind((outcome) {self:?} != {expected:?} (expected))",)
+        } else {
+            format!("{self:?} (no diff)",)
+        }
+    }
+}
+
 impl DebugDiff for Style {
     fn debug_diff(&self, expected: &Self) -> String {
         format!(
-            "Style {{\n\tflags: {},\n\tcoloring: {},\n}}",
+            "Style {{\n\tflags: {},\n\tcoloring: {},\n\tunderline_kind: {},\n}}",
             self.formats.debug_diff(&expected.formats),
             self.coloring.debug_diff(&expected.coloring),
+            self.get_underline_kind()
+                .debug_diff(&expected.get_underline_kind()),
         )
     }
 }
@@ -399,7 +436,23 @@ impl DebugDiff for StyleDelta {
                 (StyleDelta::Empty, StyleDelta::ExtraStyles(e)) => {
                     format!("StyleDelta::PrefixUsing({self:#?} != {e:#?})")
                 }
-                _ => unreachable!(),
+                (
+                    StyleDelta::TurnOff {
+                        off: o_off,
+                        then: o,
+                    },
+                    StyleDelta::TurnOff {
+                        off: e_off,
+                        then: e,
+                    },
+                ) => {
+                    format!(
+                        "StyleDelta::TurnOff {{\n\toff: {},\n\tthen: {},\n}}",
+                        o_off.debug_diff(e_off),
+                        o.debug_diff(e)
+                    )
+                }
+                _ => format!("(outcome) {self:#?} != {expected:#?} (expected)"),
             }
         }
     }
@@ -410,7 +463,6 @@ mod test {
     use crate::debug::debug_style_to_string;
     use crate::style::Color::*;
     use crate::style::{FormatFlags, Style};
-    use crate::style_test;
     use paste::paste;
 
     macro_rules! create_style_eq_tests {
@@ -450,5 +502,8 @@ mod test {
         [red: Red.as_fg(), "Style { foreground(Red) }"]
         [redblue: Red.on_bg(Rgb(3, 2, 4)), "Style { foreground(Red), background(Rgb(3, 2, 4)) }"]
         [everything: Red.on_bg(Blue).blink().bold().dimmed().hidden().italic().reverse().strikethrough().underline(), "Style { blink, bold, dimmed, hidden, italic, reverse, strikethrough, underline, foreground(Red), background(Blue) }"]
+        [single_underline: Style::new().with_underline_kind(crate::UnderlineKind::Single), "Style { underline }"]
+        [dashed_underline: Style::new().bold().dashed_underline(), "Style { bold, underline(dashed) }"]
+        [underline_color: Red.on_bg(Blue).underline().underline_color(Fixed(9)), "Style { underline, foreground(Red), background(Blue), underline_color(Fixed(9)) }"]
     );
 }
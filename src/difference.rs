@@ -1,7 +1,21 @@
-use crate::style::{Coloring, FormatFlags};
+use crate::style::{BasedOn, Color, Coloring, FormatFlags};
 
 use super::Style;
 
+/// How [`Style::compute_delta_with`] should turn off formatting or colors that
+/// are set in one style, but not in the next.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DeltaStrategy {
+    /// Reset everything, and then re-apply the whole of the next style. This is
+    /// understood by every terminal.
+    #[default]
+    Reset,
+    /// Use the specific SGR codes which turn off individual properties (for
+    /// example `22` for bold/dimmed, or `39` for the foreground color). This
+    /// usually produces much less output.
+    TurnOff,
+}
+
 /// When printing out one colored string followed by another, use one of
 /// these rules to figure out which *extra* control codes need to be sent.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
@@ -13,6 +27,10 @@ pub enum StyleDelta {
     /// control codes need to be printed.
     #[default]
     Empty,
+    /// Turn off the formats in `off` (using their specific SGR codes), and
+    /// then apply the properties in `then`. Colors which are turned off are
+    /// represented in `then` as [`Color::Default`].
+    TurnOff { off: FormatFlags, then: Style },
 }
 
 /// Tracks which styling properties exist.
@@ -31,12 +49,13 @@ pub struct BoolStyle {
 pub struct BoolColoring {
     pub foreground: bool,
     pub background: bool,
+    pub underline: bool,
 }
 
 impl BoolColoring {
     /// Check if there are no colors set.
     pub fn is_empty(&self) -> bool {
-        !(self.background || self.foreground)
+        !(self.background || self.foreground || self.underline)
     }
 }
 
@@ -45,6 +64,7 @@ impl From<Coloring> for BoolColoring {
         BoolColoring {
             foreground: coloring.fg.is_some(),
             background: coloring.bg.is_some(),
+            underline: coloring.underline.is_some(),
         }
     }
 }
@@ -83,6 +103,7 @@ impl Difference for BoolColoring {
         Self {
             foreground: self.foreground.not(),
             background: self.background.not(),
+            underline: self.underline.not(),
         }
     }
 
@@ -90,6 +111,7 @@ impl Difference for BoolColoring {
         Self {
             foreground: self.foreground.conjunction(other.foreground),
             background: self.background.conjunction(other.background),
+            underline: self.underline.conjunction(other.underline),
         }
     }
 }
@@ -128,6 +150,7 @@ impl From<Style> for BoolStyle {
             reset_prefix,
             formats,
             coloring,
+            ..
         } = style;
         Self {
             reset_prefix,
@@ -142,8 +165,6 @@ impl Style {
     /// result specifying the minimum `Style` required to change from the first
     /// (`self`) style to the `next` style.
     pub fn compute_delta(self, next: Style) -> StyleDelta {
-        println!("computing delta");
-        dbg!(self, next);
         if self == next {
             // If self is the same as next, no changes are required.
             StyleDelta::Empty
@@ -158,37 +179,132 @@ impl Style {
             // case
             let turned_off_in_next = BoolStyle::turned_off(self.into(), next.into());
             if turned_off_in_next.formats.is_empty() && turned_off_in_next.coloring.is_empty() {
-                let turned_on_from_self = BoolStyle::turned_on(self.into(), next.into());
-                let mut r = Style::default().insert_formats(turned_on_from_self.formats);
-                if self.is_fg() != next.is_fg() {
-                    r = r.set_fg(next.coloring.fg);
-                }
-                if self.is_bg() != next.is_bg() {
-                    r = r.set_bg(next.coloring.bg);
-                }
-                StyleDelta::ExtraStyles(r)
+                StyleDelta::ExtraStyles(self.turned_on(next))
             } else {
                 // If colors were turned off, we need to reset.
                 StyleDelta::ExtraStyles(next.reset_prefix())
             }
         }
     }
+
+    /// Computes the differences between two consecutive styles, like
+    /// [`Style::compute_delta`], but turning properties off as specified by
+    /// the given `strategy`.
+    ///
+    /// A full reset is still used when the `next` style is empty, or
+    /// explicitly requests one.
+    pub fn compute_delta_with(self, next: Style, strategy: DeltaStrategy) -> StyleDelta {
+        match strategy {
+            DeltaStrategy::Reset => self.compute_delta(next),
+            DeltaStrategy::TurnOff => {
+                if self == next || (next.is_empty() && !self.is_empty()) || next.is_reset_prefix() {
+                    return self.compute_delta(next);
+                }
+
+                let turned_off_in_next = BoolStyle::turned_off(self.into(), next.into());
+                let mut then = self.turned_on(next);
+                if turned_off_in_next.coloring.foreground {
+                    then = then.fg(Color::Default);
+                }
+                if turned_off_in_next.coloring.background {
+                    then = then.bg(Color::Default);
+                }
+                if turned_off_in_next.coloring.underline {
+                    then = then.underline_color(Color::Default);
+                }
+
+                let off = turned_off_in_next.formats;
+                if off.is_empty() {
+                    StyleDelta::ExtraStyles(then)
+                } else {
+                    // Some formats (such as bold and dimmed) share an "off"
+                    // code, so whichever of them survives into `next` has to
+                    // be turned on again.
+                    then = then.insert_formats(next.formats.intersection(off.sharing_off_code()));
+                    StyleDelta::TurnOff { off, then }
+                }
+            }
+        }
+    }
+
+    /// The style containing the formats turned on, and the colors changed, in
+    /// `next` relative to `self`.
+    fn turned_on(self, next: Style) -> Style {
+        let turned_on_from_self = BoolStyle::turned_on(self.into(), next.into());
+        let mut r = Style::default().insert_formats(turned_on_from_self.formats);
+        if self.is_fg() != next.is_fg() {
+            r = r.set_fg(next.coloring.fg);
+        }
+        if self.is_bg() != next.is_bg() {
+            r = r.set_bg(next.coloring.bg);
+        }
+        if self.is_underline_color() != next.is_underline_color() {
+            r = r.set_underline_color(next.coloring.underline);
+        }
+        // Switching between kinds of underline is done by writing the new
+        // kind, without turning underlining off first.
+        match next.get_underline_kind() {
+            Some(kind) if self.get_underline_kind() != Some(kind) => r.with_underline_kind(kind),
+            _ => r,
+        }
+    }
 }
 
 impl StyleDelta {
-    pub fn delta_next(self, next: Style) -> StyleDelta {
+    /// Rebase this delta onto a parent's style, so that it does not turn off
+    /// any properties provided by the parent.
+    pub fn rebase_on(self, base: Style) -> StyleDelta {
         match self {
-            StyleDelta::ExtraStyles(current) => current.compute_delta(next),
-            StyleDelta::Empty => StyleDelta::ExtraStyles(next),
+            StyleDelta::ExtraStyles(style) => StyleDelta::ExtraStyles(if style.reset_prefix {
+                style.rebase_on(base)
+            } else {
+                style
+            }),
+            StyleDelta::TurnOff { off, mut then } => {
+                let off = off.difference(base.formats);
+                then = then.insert_formats(base.formats.intersection(off.sharing_off_code()));
+                if then.coloring.fg == Some(Color::Default) && base.coloring.fg.is_some() {
+                    then.coloring.fg = base.coloring.fg;
+                }
+                if then.coloring.bg == Some(Color::Default) && base.coloring.bg.is_some() {
+                    then.coloring.bg = base.coloring.bg;
+                }
+                if then.coloring.underline == Some(Color::Default)
+                    && base.coloring.underline.is_some()
+                {
+                    then.coloring.underline = base.coloring.underline;
+                }
+                if off.is_empty() {
+                    StyleDelta::ExtraStyles(then)
+                } else {
+                    StyleDelta::TurnOff { off, then }
+                }
+            }
+            StyleDelta::Empty => StyleDelta::Empty,
         }
     }
+
+    /// The style in effect once this delta has been written after text in
+    /// the `base` style. Colors which are turned off are left as
+    /// [`Color::Default`].
+    pub(crate) fn apply_to(self, base: Style) -> Style {
+        let mut style = match self {
+            StyleDelta::ExtraStyles(style) if style.reset_prefix => style,
+            StyleDelta::ExtraStyles(style) => style.update_with(base),
+            StyleDelta::TurnOff { off, then } => then.update_with(base.remove_formats(off)),
+            StyleDelta::Empty => base,
+        };
+        style.reset_prefix = false;
+        style
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use super::DeltaStrategy;
     use super::StyleDelta::*;
     use crate::style::Color::*;
-    use crate::style::Style;
+    use crate::style::{FormatFlags, Style};
 
     fn style() -> Style {
         Style::new()
@@ -228,4 +344,94 @@ mod test {
     test!(removal_of_hidden:          style().hidden();        style()  => ExtraStyles(style().reset_prefix()));
     test!(removal_of_dimmed:          style().dimmed();        style()  => ExtraStyles(style().reset_prefix()));
     test!(removal_of_blink:           style().blink();         style()  => ExtraStyles(style().reset_prefix()));
+
+    macro_rules! test_turn_off {
+        ($name: ident: $first: expr; $next: expr => $result: expr) => {
+            #[test]
+            fn $name() {
+                let outcome = $first.compute_delta_with($next, DeltaStrategy::TurnOff);
+                let expected = $result;
+                if outcome != expected {
+                    use crate::debug::DebugDiff;
+                    let diff = outcome.debug_diff(&expected);
+                    println!("difference!\n{diff}");
+                }
+                assert_eq!(outcome, expected);
+            }
+        };
+    }
+
+    test_turn_off!(turn_off_nothing:   Green.bold(); Green.bold() => Empty);
+    test_turn_off!(turn_off_addition:  Green.as_fg(); Green.bold() => ExtraStyles(style().bold()));
+    test_turn_off!(turn_off_to_empty:  Green.bold(); style() => ExtraStyles(style().reset_prefix()));
+    test_turn_off!(turn_off_unbold:    Green.bold(); Green.as_fg() => TurnOff { off: FormatFlags::BOLD, then: style() });
+    test_turn_off!(turn_off_keep_dim:  style().bold().dimmed().italic(); style().dimmed() => TurnOff { off: FormatFlags::BOLD | FormatFlags::ITALIC, then: style().dimmed() });
+    test_turn_off!(turn_off_fg:        Green.on_bg(Red); Red.as_bg() => ExtraStyles(style().fg(Default)));
+    test_turn_off!(turn_off_bg_and_underline: Green.on_bg(Red).underline(); Blue.as_fg() => TurnOff { off: FormatFlags::UNDERLINE, then: style().fg(Blue).bg(Default) });
+    test!(underline_kind_change: Red.underline(); Red.curly_underline() => ExtraStyles(style().curly_underline()));
+    test!(underline_kind_added:  Red.as_fg(); Red.dotted_underline() => ExtraStyles(style().dotted_underline()));
+    test!(underline_kind_same:   Red.curly_underline(); Red.curly_underline().bold() => ExtraStyles(style().bold()));
+    test!(underline_kind_removed: Red.curly_underline(); Red.as_fg() => ExtraStyles(Red.as_fg().reset_prefix()));
+    test_turn_off!(turn_off_underline_kind: Red.curly_underline().bold(); Red.double_underline() => TurnOff { off: FormatFlags::BOLD, then: style().double_underline() });
+
+    test!(underline_color_added:   Red.underline(); Red.underline().underline_color(Blue) => ExtraStyles(style().underline_color(Blue)));
+    test!(underline_color_changed: Red.underline().underline_color(Green); Red.underline().underline_color(Blue) => ExtraStyles(style().underline_color(Blue)));
+    test!(underline_color_removed: Red.underline().underline_color(Green); Red.underline() => ExtraStyles(Red.underline().reset_prefix()));
+    test_turn_off!(turn_off_underline_color: Red.underline().underline_color(Green); Red.underline() => ExtraStyles(style().underline_color(Default)));
+
+    test_turn_off!(turn_off_overline:  Red.overline().framed(); Red.framed() => TurnOff { off: FormatFlags::OVERLINE, then: style() });
+    test_turn_off!(turn_off_keep_fraktur: style().italic().fraktur(); style().fraktur() => TurnOff { off: FormatFlags::ITALIC, then: style().fraktur() });
+    test_turn_off!(turn_off_keep_subscript: style().superscript().subscript().encircled(); style().subscript() => TurnOff { off: FormatFlags::SUPERSCRIPT | FormatFlags::ENCIRCLED, then: style().subscript() });
+
+    test_turn_off!(turn_off_explicit_reset: Green.bold(); Green.as_fg().reset_prefix() => ExtraStyles(Green.as_fg().reset_prefix()));
+
+    #[test]
+    fn turn_off_rebase() {
+        let delta = TurnOff {
+            off: FormatFlags::BOLD | FormatFlags::ITALIC,
+            then: style().fg(Default),
+        };
+        assert_eq!(
+            delta.rebase_on(Red.bold()),
+            TurnOff {
+                off: FormatFlags::ITALIC,
+                then: style().fg(Red),
+            }
+        );
+        assert_eq!(
+            delta.rebase_on(Red.dimmed().italic()),
+            TurnOff {
+                off: FormatFlags::BOLD,
+                then: Red.dimmed(),
+            }
+        );
+    }
+
+    #[test]
+    fn apply_to() {
+        let styles = [
+            Green.bold(),
+            Green.as_fg(),
+            Green.on_bg(Red).underline(),
+            Blue.as_fg(),
+            style().bold().dimmed().italic(),
+            style().dimmed(),
+            Red.curly_underline().bold(),
+            Red.double_underline(),
+            style(),
+        ];
+        for strategy in [DeltaStrategy::Reset, DeltaStrategy::TurnOff] {
+            for pair in styles.windows(2) {
+                let delta = pair[0].compute_delta_with(pair[1], strategy);
+                let applied = delta.apply_to(pair[0]);
+                // Colors which are turned off come back as the default color.
+                let off = |color: Option<_>| color.filter(|&color| color != Default);
+                let applied = applied
+                    .set_fg(off(applied.is_fg()))
+                    .set_bg(off(applied.is_bg()))
+                    .set_underline_color(off(applied.is_underline_color()));
+                assert_eq!(applied, pair[1], "{:?} then {:?}", pair[0], pair[1]);
+            }
+        }
+    }
 }
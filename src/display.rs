@@ -1,10 +1,12 @@
 use crate::ansi::RESET;
-use crate::difference::StyleDelta;
+use crate::capabilities::Capabilities;
+use crate::difference::{DeltaStrategy, StyleDelta};
 use crate::style::{BasedOn, Color, Style};
-use crate::write::{AnyWrite, Content, StrLike, WriteResult};
+use crate::write::{AnyWrite, Content, FmtRenderer, StrLike, WriteResult};
 use crate::{fmt_write, io_write, write_fmt, write_str};
 use std::borrow::Cow;
 use std::cell::{Ref, RefCell, RefMut};
+use std::collections::BTreeMap;
 use std::fmt::{self, Debug};
 use std::io;
 
@@ -16,6 +18,17 @@ pub enum OSControl<'a, S: 'a + ToOwned + ?Sized> {
     Link {
         /// The url underlying the clickable link.
         url: Content<'a, S>,
+        /// The `id` parameter of the link. Terminals treat strings with the
+        /// same url and id as one link, even when they are not next to each
+        /// other (say, because the link was wrapped onto several lines).
+        id: Option<String>,
+        /// Any other parameters of the link, written as `key=value` pairs.
+        ///
+        /// Characters which cannot appear in a parameter (`:`, `;`, `=`, `%`,
+        /// whitespace, control characters and anything outside ASCII) are
+        /// `%`-encoded when written, in the id as well, and decoded again by
+        /// [`parse_ansi`](crate::parse_ansi).
+        params: BTreeMap<String, String>,
     },
 }
 
@@ -28,7 +41,12 @@ where
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Title => write!(f, "Title"),
-            Self::Link { url } => f.debug_struct("Link").field("url", url).finish(),
+            Self::Link { url, id, params } => f
+                .debug_struct("Link")
+                .field("url", url)
+                .field("id", id)
+                .field("params", params)
+                .finish(),
         }
     }
 }
@@ -36,12 +54,35 @@ where
 impl<'a, S: 'a + ToOwned + ?Sized> Clone for OSControl<'a, S> {
     fn clone(&self) -> Self {
         match self {
-            Self::Link { url: u } => Self::Link { url: u.clone() },
+            Self::Link { url, id, params } => Self::Link {
+                url: url.clone(),
+                id: id.clone(),
+                params: params.clone(),
+            },
             Self::Title => Self::Title,
         }
     }
 }
 
+/// A key or value of a hyperlink parameter, which is written with the
+/// characters that would end it (or the whole escape sequence) `%`-encoded.
+struct LinkParam<'s>(&'s str);
+
+impl fmt::Display for LinkParam<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            if c.is_ascii_graphic() && !matches!(c, ':' | ';' | '=' | '%') {
+                write!(f, "{c}")?;
+            } else {
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    write!(f, "%{byte:02X}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 /// An `AnsiGenericString` includes a generic string type and a `Style` to
 /// display that string.  `AnsiString` and `AnsiByteString` are aliases for
 /// this type on `str` and `\[u8]`, respectively.
@@ -141,6 +182,18 @@ impl<'a, S: 'a + ToOwned + ?Sized> From<AnsiGenericStrings<'a, S>> for AnsiGener
     }
 }
 
+impl<'a, S: 'a + ToOwned + ?Sized> From<Box<dyn FmtRenderer<'a, S>>>
+    for AnsiGenericString<'a, S>
+{
+    fn from(renderer: Box<dyn FmtRenderer<'a, S>>) -> Self {
+        AnsiGenericString {
+            style: Style::default(),
+            content: renderer.into(),
+            oscontrol: None,
+        }
+    }
+}
+
 /// An ANSI String is a string coupled with the `Style` to display it
 /// in a terminal.
 ///
@@ -289,7 +342,11 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericString<'a, S> {
     where
         I: Into<Content<'a, S>>,
     {
-        self.oscontrol = Some(OSControl::Link { url: url.into() });
+        self.oscontrol = Some(OSControl::Link {
+            url: url.into(),
+            id: None,
+            params: BTreeMap::new(),
+        });
         self
     }
 
@@ -312,16 +369,65 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericString<'a, S> {
             content: self.content,
             oscontrol: Some(OSControl::Link {
                 url: Content::StrLike(Cow::Borrowed(url)),
+                id: None,
+                params: BTreeMap::new(),
             }),
         }
     }
 
+    /// Set the `id` parameter of this string's hyperlink, so that terminals
+    /// treat it as part of the same link as other strings with the same url
+    /// and id. This has no effect if the string is not a hyperlink.
+    ///
+    /// See [`with_link_ids`](crate::utils::with_link_ids) to generate ids
+    /// automatically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::Color::Blue;
+    ///
+    /// let link_string = Blue
+    ///     .paint("the manual")
+    ///     .hyperlink("https://www.example.com")
+    ///     .with_link_id("manual");
+    /// assert_eq!(
+    ///     link_string.to_string(),
+    ///     "\x1B[34m\x1B]8;id=manual;https://www.example.com\x1B\\the manual\x1B]8;;\x1B\\\x1B[0m"
+    /// );
+    /// ```
+    pub fn with_link_id(mut self, id: impl Into<String>) -> Self {
+        if let Some(OSControl::Link { id: link_id, .. }) = &mut self.oscontrol {
+            *link_id = Some(id.into());
+        }
+        self
+    }
+
+    /// Set a parameter of this string's hyperlink, other than its `id` (see
+    /// [`with_link_id`](Self::with_link_id)). This has no effect if the string
+    /// is not a hyperlink. Characters which cannot appear in a parameter are
+    /// `%`-encoded when it is written (see [`OSControl::Link`]).
+    pub fn with_link_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        if let Some(OSControl::Link { params, .. }) = &mut self.oscontrol {
+            params.insert(key.into(), value.into());
+        }
+        self
+    }
+
+    /// Get the `id` parameter of this string's hyperlink, if it has one.
+    pub fn link_id(&self) -> Option<&str> {
+        match &self.oscontrol {
+            Some(OSControl::Link { id, .. }) => id.as_deref(),
+            _ => None,
+        }
+    }
+
     /// Get the url content for this string's oscontrol.
     pub const fn url_string(&self) -> Option<&Content<'a, S>> {
         if let Some(osc) = &self.oscontrol {
             match osc {
                 OSControl::Title => {}
-                OSControl::Link { url } => {
+                OSControl::Link { url, .. } => {
                     return Some(url);
                 }
             }
@@ -335,6 +441,7 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericString<'a, S> {
 pub struct AnsiGenericStrings<'a, S: 'a + ToOwned + ?Sized> {
     strings: Cow<'a, [AnsiGenericString<'a, S>]>,
     style_updates: RefCell<Cow<'a, [StyleUpdate]>>,
+    delta_strategy: DeltaStrategy,
 }
 
 impl<'a, S: 'a + ToOwned + ?Sized> From<AnsiGenericString<'a, S>> for AnsiGenericStrings<'a, S> {
@@ -346,6 +453,7 @@ impl<'a, S: 'a + ToOwned + ?Sized> From<AnsiGenericString<'a, S>> for AnsiGeneri
                 style_delta: StyleDelta::ExtraStyles(style),
                 begins_at: 0,
             }])),
+            delta_strategy: DeltaStrategy::default(),
         }
     }
 }
@@ -355,6 +463,7 @@ impl<'a, S: 'a + ToOwned + ?Sized> Clone for AnsiGenericStrings<'a, S> {
         Self {
             style_updates: RefCell::new(self.style_updates.borrow_mut().clone()),
             strings: self.strings.clone(),
+            delta_strategy: self.delta_strategy,
         }
     }
 }
@@ -369,6 +478,7 @@ where
         f.debug_struct("AnsiGenericStrings")
             .field("strings", &self.strings)
             .field("style_updates", &self.style_updates.borrow_mut())
+            .field("delta_strategy", &self.delta_strategy)
             .finish()
     }
 }
@@ -378,6 +488,7 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
         Self {
             strings: Cow::Borrowed(strings),
             style_updates: RefCell::new(Cow::Borrowed(&[])),
+            delta_strategy: DeltaStrategy::Reset,
         }
     }
     /// Create empty sequence with the given capacity.
@@ -385,9 +496,37 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
         Self {
             strings: Vec::with_capacity(capacity).into(),
             style_updates: RefCell::new(Vec::with_capacity(capacity).into()),
+            delta_strategy: DeltaStrategy::default(),
         }
     }
 
+    /// Use the given strategy to turn off styling between consecutive strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(not(feature = "gnu_legacy"))]
+    /// # {
+    /// use procr_ansi_term::{AnsiStrings, Color::Red, DeltaStrategy};
+    ///
+    /// let strings = AnsiStrings([Red.bold().paint("bold"), Red.paint(" not bold")]);
+    /// assert_eq!(
+    ///     strings.with_delta_strategy(DeltaStrategy::TurnOff).to_string(),
+    ///     "\x1B[1;31mbold\x1B[22m not bold\x1B[0m"
+    /// );
+    /// # }
+    /// ```
+    pub fn with_delta_strategy(mut self, strategy: DeltaStrategy) -> Self {
+        self.delta_strategy = strategy;
+        self.calculate_style_updates();
+        self
+    }
+
+    /// The strategy used to turn off styling between consecutive strings.
+    pub const fn delta_strategy(&self) -> DeltaStrategy {
+        self.delta_strategy
+    }
+
     /// Iterate over the underlying generic strings.
     pub fn iter(&self) -> impl Iterator<Item = &'_ AnsiGenericString<'a, S>> {
         self.strings.iter()
@@ -395,8 +534,8 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
 
     fn calculate_style_updates(&self) {
         let mut style_updates = Vec::with_capacity(self.strings.len());
-        for (ix, string) in self.strings.iter().enumerate() {
-            Self::push_style_into(&mut style_updates, string.style, ix);
+        for ix in 0..self.strings.len() {
+            Self::push_style_into(&mut style_updates, &self.strings, ix, self.delta_strategy);
         }
         *self.style_updates.borrow_mut() = Cow::Owned(style_updates);
     }
@@ -436,7 +575,7 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
         }
 
         // Now we know updates are not empty.
-        updates.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        updates.sort_unstable_by_key(|u| u.0);
         let min_changed_ix = updates.first().unwrap().0;
 
         let mut new_strings = self.strings.to_vec();
@@ -455,20 +594,22 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
             let mut new_style_updates = Vec::with_capacity(new_strings.len());
             new_style_updates.extend(unchanged_existing);
 
-            for (ix, style) in new_strings[min_changed_ix..]
-                .iter()
-                .map(|s| s.style)
-                .enumerate()
-            {
-                Self::push_style_into(&mut new_style_updates, style, ix + min_changed_ix)
+            for ix in min_changed_ix..new_strings.len() {
+                Self::push_style_into(
+                    &mut new_style_updates,
+                    &new_strings,
+                    ix,
+                    self.delta_strategy,
+                )
             }
 
             Self {
                 strings: Cow::Owned(new_strings),
                 style_updates: RefCell::new(Cow::Owned(new_style_updates)),
+                delta_strategy: self.delta_strategy,
             }
         } else {
-            Self::from_iter(new_strings)
+            Self::from_iter(new_strings).with_delta_strategy(self.delta_strategy)
         }
     }
 
@@ -476,14 +617,7 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
     /// "OR" operation.
     pub fn rebase_on(self, base: Style) -> Self {
         for update in self.style_updates_mut().to_mut().iter_mut() {
-            update.style_delta = match update.style_delta {
-                StyleDelta::ExtraStyles(style) => StyleDelta::ExtraStyles(if style.reset_prefix {
-                    style.rebase_on(base)
-                } else {
-                    style
-                }),
-                StyleDelta::Empty => StyleDelta::Empty,
-            };
+            update.style_delta = update.style_delta.rebase_on(base);
         }
         self
     }
@@ -491,22 +625,24 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
     /// Push given generic string into this [`AnsiGenericStrings`] instance.
     #[inline]
     pub fn push(&mut self, s: AnsiGenericString<'a, S>) {
-        self.strings.to_mut().push(s.clone());
-        self.push_style(*s.style_ref(), self.strings.len() - 1);
+        self.strings.to_mut().push(s);
+        self.push_style(self.strings.len() - 1);
     }
 
+    /// Push the style update for the string at `begins_at`, which is computed
+    /// relative to the (full) style of the string before it.
     #[inline]
     fn push_style_into(
         existing_style_updates: &mut Vec<StyleUpdate>,
-        next: Style,
+        strings: &[AnsiGenericString<'a, S>],
         begins_at: usize,
+        strategy: DeltaStrategy,
     ) {
-        let command = existing_style_updates
-            .last()
-            .copied()
-            .unwrap_or_default()
-            .style_delta
-            .delta_next(next);
+        let next = strings[begins_at].style;
+        let command = match begins_at.checked_sub(1) {
+            Some(previous) => strings[previous].style.compute_delta_with(next, strategy),
+            None => StyleDelta::ExtraStyles(next),
+        };
 
         existing_style_updates.push(StyleUpdate {
             begins_at,
@@ -515,15 +651,20 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
     }
 
     #[inline]
-    fn push_style(&self, next: Style, begins_at: usize) {
-        Self::push_style_into(self.style_updates.borrow_mut().to_mut(), next, begins_at)
+    fn push_style(&self, begins_at: usize) {
+        Self::push_style_into(
+            self.style_updates.borrow_mut().to_mut(),
+            &self.strings,
+            begins_at,
+            self.delta_strategy,
+        )
     }
 
-    fn write_iter(&self) -> WriteIter<'_, 'a, S> {
+    pub(crate) fn write_iter(&self) -> WriteIter<'_, 'a, S> {
         WriteIter {
             style_iter: StyleIter {
                 cursor: 0,
-                instructions: self.style_updates.borrow(),
+                instructions: self.style_updates(),
                 next_update: None,
                 current: None,
             },
@@ -676,7 +817,7 @@ impl Style {
     {
         AnsiGenericString {
             content: match input.into() {
-                x @ Content::GenericStrings(_) => x.with_context(self),
+                x @ (Content::GenericStrings(_) | Content::Renderer(_)) => x.with_context(self),
                 x => x,
             },
             style: self,
@@ -738,20 +879,48 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericString<'a, S> {
         S: StrLike<'a, W>,
         str: StrLike<'a, W>,
     {
+        Self::write_inner_with(content, oscontrol, w, Capabilities::global())
+    }
+
+    /// Write only the part of the generic string which lies within its styling
+    /// prefix and suffix, as supported by the given `capabilities`. Hyperlinks
+    /// which are not supported are written as their plain content, and titles
+    /// which are not supported are not written at all. Hyperlinks and titles
+    /// are ended with the terminator given by `capabilities`.
+    pub fn write_inner_with<W: AnyWrite + ?Sized>(
+        content: &Content<'a, S>,
+        oscontrol: &Option<OSControl<'a, S>>,
+        w: &mut W,
+        capabilities: Capabilities,
+    ) -> WriteResult<W::Error>
+    where
+        S: StrLike<'a, W>,
+        str: StrLike<'a, W>,
+    {
+        let end = capabilities.osc_terminator.as_str();
         match oscontrol {
-            Some(OSControl::Link { url: u, .. }) => {
+            Some(OSControl::Link { url: u, id, params }) if capabilities.hyperlinks => {
+                write_str!(w, "\x1B]8;")?;
+                let id = id.iter().map(|id| ("id", id.as_str()));
+                let params = params.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+                for (ix, (key, value)) in id.chain(params).enumerate() {
+                    let sep = if ix == 0 { "" } else { ":" };
+                    write_fmt!(w, "{sep}{}={}", LinkParam(key), LinkParam(value))?;
+                }
+                write_str!(w, ";")?;
+                u.write_to_with(w, capabilities)?;
+                write_str!(w, end)?;
+                content.write_to_with(w, capabilities)?;
                 write_str!(w, "\x1B]8;;")?;
-                u.write_to(w)?;
-                write_str!(w, "\x1B\x5C")?;
-                content.write_to(w)?;
-                write_str!(w, "\x1B]8;;\x1B\x5C")
+                write_str!(w, end)
             }
-            Some(OSControl::Title) => {
+            Some(OSControl::Title) if capabilities.titles => {
                 write_str!(w, "\x1B]2;")?;
-                content.write_to(w)?;
-                write_str!(w, "\x1B\x5C")
+                content.write_to_with(w, capabilities)?;
+                write_str!(w, end)
             }
-            None => content.write_to(w),
+            Some(OSControl::Title) => Ok(()),
+            Some(OSControl::Link { .. }) | None => content.write_to_with(w, capabilities),
         }
     }
 
@@ -761,9 +930,37 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericString<'a, S> {
         S: StrLike<'a, W>,
         str: StrLike<'a, W>,
     {
-        write_fmt!(w, "{}", self.style.prefix())?;
-        Self::write_inner(&self.content, &self.oscontrol, w)?;
-        write_fmt!(w, "{}", self.style.suffix())
+        self.write_to_any_with(w, Capabilities::global())
+    }
+
+    /// Write this generic string to the given `AnyWrite` implementor, using
+    /// only the escape sequences supported by the given `capabilities`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fmt;
+    /// use procr_ansi_term::{fmt_write, Capabilities, Color::Red};
+    ///
+    /// let link = Red.paint("docs").hyperlink("https://example.com");
+    /// let mut s = String::new();
+    /// link.write_to_any_with(fmt_write!(&mut s), Capabilities::none())
+    ///     .unwrap();
+    /// assert_eq!(s, "docs");
+    /// ```
+    pub fn write_to_any_with<W: AnyWrite + ?Sized>(
+        &self,
+        w: &mut W,
+        capabilities: Capabilities,
+    ) -> WriteResult<W::Error>
+    where
+        S: StrLike<'a, W>,
+        str: StrLike<'a, W>,
+    {
+        let depth = capabilities.color_depth;
+        self.style.write_prefix_with_depth(w, depth)?;
+        Self::write_inner_with(&self.content, &self.oscontrol, w, capabilities)?;
+        self.style.write_suffix_with_depth(w, depth)
     }
 }
 
@@ -791,21 +988,31 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
         S: StrLike<'a, W>,
         str: StrLike<'a, W>,
     {
-        let mut last_is_plain = true;
+        self.write_to_any_with(w, Capabilities::global())
+    }
 
+    /// Write this sequence to the given [`AnyWrite`] implementor, using only
+    /// the escape sequences supported by the given `capabilities`.
+    pub fn write_to_any_with<W: AnyWrite + ?Sized>(
+        &self,
+        w: &mut W,
+        capabilities: Capabilities,
+    ) -> WriteResult<W::Error>
+    where
+        S: StrLike<'a, W>,
+        str: StrLike<'a, W>,
+    {
+        let depth = capabilities.color_depth;
+        // The style the terminal is left in, which may be inherited (through
+        // `rebase_on`) rather than being the last string's own style.
+        let mut written = Style::new();
         for (style_command, content, oscontrol) in self.write_iter() {
-            match style_command {
-                StyleDelta::ExtraStyles(style) => {
-                    style.write_prefix(w)?;
-                    last_is_plain = style.has_no_styling();
-                }
-                StyleDelta::Empty => {}
-            }
-            AnsiGenericString::write_inner(&content, &oscontrol, w)?;
+            style_command.write_prefix_with_depth(w, depth)?;
+            written = style_command.apply_to(written);
+            AnsiGenericString::write_inner_with(&content, &oscontrol, w, capabilities)?;
         }
 
-        if last_is_plain {
-            dbg!(last_is_plain);
+        if written.downgrade(depth).has_no_styling() {
             Ok(())
         } else {
             w.write_str(RESET.as_ref())
@@ -819,8 +1026,10 @@ impl<'a, S: 'a + ToOwned + ?Sized> AnsiGenericStrings<'a, S> {
 mod tests {
     pub use super::super::{AnsiGenericString, AnsiStrings};
     use crate::assert_required;
+    use crate::fmt_write;
     pub use crate::style::Color::*;
     pub use crate::style::Style;
+    use std::fmt;
 
     #[test]
     fn no_control_codes_for_plain() {
@@ -887,7 +1096,7 @@ mod tests {
             format_args!(
                 "{}{}{}",
                 after_g.style.prefix(),
-                after_g.content.to_string(),
+                after_g.content,
                 after_g.style.suffix()
             )
         });
@@ -940,6 +1149,15 @@ mod tests {
         assert_required!(joined, expected);
     }
 
+    #[test]
+    fn rebased_plain_last() {
+        // The last string is plain, but is written bold, so it still needs
+        // a reset.
+        let strings = AnsiStrings([Red.bold().paint("a"), Style::new().paint("b")])
+            .rebase_on(Style::new().bold());
+        assert_eq!(strings.to_string(), "\x1B[1;31ma\x1B[0m\x1B[1mb\x1B[0m");
+    }
+
     #[test]
     fn hyperlink() {
         let styled = Red
@@ -951,6 +1169,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hyperlink_params() {
+        let styled = Red
+            .paint("Link to example.com.")
+            .hyperlink("https://example.com")
+            .with_link_param("lang", "en")
+            .with_link_id("ex")
+            .with_link_param("a", "1");
+        assert_eq!(styled.link_id(), Some("ex"));
+        assert_eq!(
+            styled.to_string(),
+            "\x1B[31m\x1B]8;id=ex:a=1:lang=en;https://example.com\x1B\\Link to example.com.\x1B]8;;\x1B\\\x1B[0m"
+        );
+
+        let escaped = Red
+            .paint("x")
+            .hyperlink("https://example.com")
+            .with_link_id("a:b;c")
+            .with_link_param("k=", "\x1B\\100% é");
+        assert_eq!(
+            escaped.to_string(),
+            "\x1B[31m\x1B]8;id=a%3Ab%3Bc:k%3D=%1B\\100%25%20%C3%A9;https://example.com\x1B\\x\x1B]8;;\x1B\\\x1B[0m"
+        );
+
+        let plain = Red.paint("No link.").with_link_id("ex");
+        assert_eq!(plain.link_id(), None);
+        assert_eq!(plain.to_string(), "\x1B[31mNo link.\x1B[0m");
+    }
+
     #[test]
     fn hperlinks_link_only() {
         let link = Blue
@@ -1015,4 +1262,89 @@ mod tests {
         #[cfg(not(feature = "gnu_legacy"))]
         assert_eq!(joined, format!("\x1B[32mBefore link. \x1B[4;34m\x1B]8;;https://example.com\x1B\\Link to example.com.\x1B]8;;\x1B\\\x1B[0m\x1B[32m After link.\x1B[0m"));
     }
+
+    #[test]
+    fn turn_off_strategy() {
+        use crate::DeltaStrategy;
+
+        let strings = AnsiStrings([
+            Red.bold().italic().paint("a"),
+            Red.italic().paint("b"),
+            Blue.on_bg(Black).paint("c"),
+            Blue.paint("d"),
+            Style::new().paint("e"),
+        ]);
+        assert_eq!(strings.delta_strategy(), DeltaStrategy::Reset);
+        let joined = strings
+            .with_delta_strategy(DeltaStrategy::TurnOff)
+            .to_string();
+        #[cfg(feature = "gnu_legacy")]
+        assert_eq!(
+            joined,
+            "\x1B[01;03;31ma\x1B[22mb\x1B[23;40;34mc\x1B[49md\x1B[0me"
+        );
+        #[cfg(not(feature = "gnu_legacy"))]
+        assert_eq!(
+            joined,
+            "\x1B[1;3;31ma\x1B[22mb\x1B[23;40;34mc\x1B[49md\x1B[0me"
+        );
+    }
+
+    #[test]
+    fn deltas_use_previous_full_style() {
+        // The third string's delta must be computed from the full style of the
+        // second string, not from the delta used to reach it.
+        let joined = AnsiStrings([
+            Red.bold().paint("a"),
+            Red.bold().italic().paint("b"),
+            Blue.bold().italic().paint("c"),
+        ])
+        .to_string();
+        #[cfg(feature = "gnu_legacy")]
+        assert_eq!(joined, "\x1B[01;31ma\x1B[03mb\x1B[34mc\x1B[0m");
+        #[cfg(not(feature = "gnu_legacy"))]
+        assert_eq!(joined, "\x1B[1;31ma\x1B[3mb\x1B[34mc\x1B[0m");
+    }
+
+    #[test]
+    fn write_with_capabilities() {
+        use crate::Capabilities;
+
+        let strings = AnsiStrings([
+            AnsiGenericString::title("title"),
+            Rgb(250, 5, 5).paint("red "),
+            Blue.underline()
+                .paint("link")
+                .hyperlink("https://example.com"),
+        ]);
+        let write = |capabilities| {
+            let mut s = String::new();
+            strings
+                .write_to_any_with(fmt_write!(&mut s), capabilities)
+                .unwrap();
+            s
+        };
+
+        assert_eq!(write(Capabilities::all()), strings.to_string());
+        #[cfg(not(feature = "gnu_legacy"))]
+        assert_eq!(
+            write(Capabilities {
+                color_depth: crate::ColorDepth::Ansi16,
+                hyperlinks: false,
+                titles: false,
+                osc_terminator: crate::OscTerminator::St,
+            }),
+            "\x1B[91mred \x1B[4;34mlink\x1B[0m"
+        );
+        #[cfg(not(feature = "gnu_legacy"))]
+        assert_eq!(
+            write(Capabilities {
+                osc_terminator: crate::OscTerminator::Bel,
+                ..Capabilities::all()
+            }),
+            "\x1B]2;title\x07\x1B[38;2;250;5;5mred \x1B[4;34m\x1B]8;;https://example.com\x07link\x1B]8;;\x07\x1B[0m"
+        );
+        #[cfg(not(feature = "gnu_legacy"))]
+        assert_eq!(write(Capabilities::none()), "red \x1B[4mlink\x1B[0m");
+    }
 }
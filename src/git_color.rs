@@ -0,0 +1,384 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::style::{Color, FormatFlags, Style};
+use crate::style_str::{parse_hex, set_color, tokens, ParseStyleError, ParseStyleErrorKind, Token};
+
+/// Git's attribute names, and the flags they stand for.
+const ATTRIBUTES: [(&str, FormatFlags); 7] = [
+    ("bold", FormatFlags::BOLD),
+    ("dim", FormatFlags::DIMMED),
+    ("italic", FormatFlags::ITALIC),
+    ("ul", FormatFlags::UNDERLINE),
+    ("blink", FormatFlags::BLINK),
+    ("reverse", FormatFlags::REVERSE),
+    ("strike", FormatFlags::STRIKETHROUGH),
+];
+
+/// Git's color names. Each has a `bright` version too, except `default`.
+const COLORS: [(&str, Color, Color); 8] = [
+    ("black", Color::Black, Color::DarkGray),
+    ("red", Color::Red, Color::LightRed),
+    ("green", Color::Green, Color::LightGreen),
+    ("yellow", Color::Yellow, Color::LightYellow),
+    ("blue", Color::Blue, Color::LightBlue),
+    ("magenta", Color::Magenta, Color::LightMagenta),
+    ("cyan", Color::Cyan, Color::LightCyan),
+    ("white", Color::White, Color::LightGray),
+];
+
+/// A color setting in the syntax of `git config`, such as the value of
+/// `color.diff.meta = "bold yellow ul"`.
+///
+/// Git's colors can turn attributes off (`nobold` or `no-bold`), which a
+/// [`Style`] can't express by itself: those attributes are kept in
+/// [`GitColor::removed`], and taken away by [`GitColor::apply_to`].
+///
+/// ```
+/// use procr_ansi_term::{Color, GitColor};
+///
+/// let meta: GitColor = "bold yellow ul".parse().unwrap();
+/// assert_eq!(meta.style, Color::Yellow.bold().underline());
+///
+/// let plain: GitColor = "normal blue nobold".parse().unwrap();
+/// assert_eq!(plain.apply_to(meta.style), Color::Yellow.underline().on(Color::Blue));
+/// assert_eq!(plain.to_string(), "normal blue nobold");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GitColor {
+    /// The colors and attributes that are set. `reset` sets
+    /// [`Style::reset_prefix`].
+    pub style: Style,
+    /// The attributes that are turned off.
+    pub removed: FormatFlags,
+}
+
+impl GitColor {
+    /// Apply this color on top of `base`, the way git layers a color over
+    /// the one in effect: colors and attributes that are set take priority
+    /// (as with [`Style::update_with`]), and removed attributes are taken
+    /// away. After `reset`, nothing is kept from `base`.
+    pub fn apply_to(self, base: Style) -> Style {
+        let style = if self.style.is_reset_prefix() {
+            self.style
+        } else {
+            self.style.update_with(base)
+        };
+        style.remove_formats(self.removed)
+    }
+}
+
+impl From<Style> for GitColor {
+    fn from(style: Style) -> Self {
+        GitColor {
+            style,
+            removed: FormatFlags::empty(),
+        }
+    }
+}
+
+/// A git color word: `None` stands for `normal`, which leaves the color as
+/// it is.
+fn parse_git_color(token: Token<'_>) -> Result<Option<Option<Color>>, ParseStyleError> {
+    let word = token.text;
+    let invalid = || ParseStyleError::new(token, ParseStyleErrorKind::InvalidColor);
+
+    if word.eq_ignore_ascii_case("normal") {
+        return Ok(Some(None));
+    }
+    if word.eq_ignore_ascii_case("default") {
+        return Ok(Some(Some(Color::Default)));
+    }
+    if let Some(hex) = word.strip_prefix('#') {
+        // `#rgb` is short for `#rrggbb`.
+        let long;
+        let hex = if hex.len() == 3 && hex.is_ascii() {
+            long = hex.chars().flat_map(|c| [c, c]).collect::<String>();
+            &long
+        } else {
+            hex
+        };
+        return parse_hex(hex).map(|c| Some(Some(c))).ok_or_else(invalid);
+    }
+    if word.bytes().all(|b| b.is_ascii_digit()) || word == "-1" {
+        return match word.parse::<i16>() {
+            Ok(-1) => Ok(Some(None)),
+            Ok(n) => u8::try_from(n)
+                .map(|n| Some(Some(Color::Fixed(n))))
+                .map_err(|_| invalid()),
+            Err(_) => Err(invalid()),
+        };
+    }
+
+    let lower = word.to_ascii_lowercase();
+    let (name, bright) = match lower.strip_prefix("bright") {
+        Some(name) => (name, true),
+        None => (lower.as_str(), false),
+    };
+    Ok(COLORS
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|&(_, color, bright_color)| Some(if bright { bright_color } else { color })))
+}
+
+/// A git attribute word, with whether it is negated.
+fn parse_attribute(word: &str) -> Option<(FormatFlags, bool)> {
+    let lower = word.to_ascii_lowercase();
+    let (name, negated) = match lower.strip_prefix("no") {
+        Some(name) => (name.strip_prefix('-').unwrap_or(name), true),
+        None => (lower.as_str(), false),
+    };
+    ATTRIBUTES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|&(_, flag)| (flag, negated))
+}
+
+impl FromStr for GitColor {
+    type Err = ParseStyleError;
+
+    /// Parse git's color syntax: whitespace separated words, which may be
+    ///
+    /// - up to two colors, the foreground and then the background: a name
+    ///   (`red`, or `brightred` for the bright version), a number from `0`
+    ///   to `255`, `#rrggbb` or `#rgb`, `default`, or `normal` to leave the
+    ///   color unchanged;
+    /// - attributes (`bold`, `dim`, `italic`, `ul`, `blink`, `reverse` or
+    ///   `strike`), each of which is turned off when prefixed with `no` or
+    ///   `no-`;
+    /// - `reset`, to start from a plain style.
+    ///
+    /// Words are matched ignoring case, and the error points at the first
+    /// word which could not be understood.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut git_color = GitColor::default();
+        let mut fg = None;
+        let mut bg = None;
+        let mut colors = 0;
+
+        for token in tokens(s) {
+            let word = token.text;
+            if word.eq_ignore_ascii_case("reset") {
+                git_color.style = git_color.style.reset_prefix();
+            } else if let Some(color) = parse_git_color(token)? {
+                colors += 1;
+                match (colors, color) {
+                    (1, Some(color)) => set_color(&mut fg, color, token)?,
+                    (2, Some(color)) => set_color(&mut bg, color, token)?,
+                    (1 | 2, None) => {}
+                    _ => {
+                        return Err(ParseStyleError::new(
+                            token,
+                            ParseStyleErrorKind::DuplicateColor,
+                        ))
+                    }
+                }
+            } else if let Some((flag, negated)) = parse_attribute(word) {
+                if negated {
+                    git_color.style = git_color.style.remove_formats(flag);
+                    git_color.removed |= flag;
+                } else {
+                    git_color.style = git_color.style.insert_formats(flag);
+                    git_color.removed = git_color.removed.unset_flags(flag);
+                }
+            } else {
+                return Err(ParseStyleError::new(
+                    token,
+                    ParseStyleErrorKind::UnknownWord,
+                ));
+            }
+        }
+
+        git_color.style = git_color.style.set_fg(fg).set_bg(bg);
+        Ok(git_color)
+    }
+}
+
+/// Write a color the way git names it.
+fn write_git_color(f: &mut fmt::Formatter<'_>, color: Color) -> fmt::Result {
+    let color = match color {
+        Color::Purple => Color::Magenta,
+        Color::LightPurple => Color::LightMagenta,
+        color => color,
+    };
+    match color {
+        Color::Fixed(n) => write!(f, "{}", n),
+        Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+        Color::Default => f.write_str("default"),
+        color => {
+            let (name, bright) = COLORS
+                .iter()
+                .find_map(|&(name, normal, bright)| {
+                    (color == normal || color == bright).then_some((name, color == bright))
+                })
+                .expect("every other color is named");
+            if bright {
+                f.write_str("bright")?;
+            }
+            f.write_str(name)
+        }
+    }
+}
+
+impl fmt::Display for GitColor {
+    /// Write this color in git's syntax: `reset` if it is set, then the
+    /// colors (using `normal` for a missing foreground), then the attributes
+    /// turned on and off.
+    ///
+    /// Git has no attribute for [`FormatFlags::HIDDEN`],
+    /// [`FormatFlags::OVERLINE`], [`FormatFlags::RAPID_BLINK`],
+    /// [`FormatFlags::FRAKTUR`], [`FormatFlags::FRAMED`],
+    /// [`FormatFlags::ENCIRCLED`], [`FormatFlags::SUPERSCRIPT`],
+    /// [`FormatFlags::SUBSCRIPT`] or [`FormatFlags::DOUBLY_UNDERLINED`], so
+    /// they are left out.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut separate = |f: &mut fmt::Formatter<'_>| {
+            if !std::mem::take(&mut first) {
+                f.write_str(" ")?;
+            }
+            Ok(())
+        };
+
+        if self.style.is_reset_prefix() {
+            separate(f)?;
+            f.write_str("reset")?;
+        }
+        match (self.style.is_fg(), self.style.is_bg()) {
+            (fg, Some(bg)) => {
+                separate(f)?;
+                match fg {
+                    Some(fg) => write_git_color(f, fg)?,
+                    None => f.write_str("normal")?,
+                }
+                f.write_str(" ")?;
+                write_git_color(f, bg)?;
+            }
+            (Some(fg), None) => {
+                separate(f)?;
+                write_git_color(f, fg)?;
+            }
+            (None, None) => {}
+        }
+        for (name, flag) in ATTRIBUTES {
+            if self.style.formats.contains(flag) {
+                separate(f)?;
+                f.write_str(name)?;
+            } else if self.removed.contains(flag) {
+                separate(f)?;
+                write!(f, "no{}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::style::Color::*;
+
+    fn parse(s: &str) -> GitColor {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse("red").style, Red.as_fg());
+        assert_eq!(parse("Yellow BrightBlack").style, Yellow.on_bg(DarkGray));
+        assert_eq!(parse("brightwhite").style, LightGray.as_fg());
+        assert_eq!(parse("normal 236").style, Style::new().on(Fixed(236)));
+        assert_eq!(parse("-1 default").style, Style::new().on(Default));
+        assert_eq!(
+            parse("#ff8800 #F80").style,
+            Rgb(255, 136, 0).on_bg(Rgb(255, 136, 0))
+        );
+        assert_eq!(parse("").style, Style::new());
+    }
+
+    #[test]
+    fn attributes() {
+        assert_eq!(parse("bold yellow ul").style, Yellow.bold().underline());
+        assert_eq!(
+            parse("dim italic blink reverse strike").style,
+            Style::new()
+                .dimmed()
+                .italic()
+                .blink()
+                .reverse()
+                .strikethrough()
+        );
+
+        let color = parse("reset nobold no-ul ITALIC noitalic");
+        assert!(color.style.is_reset_prefix());
+        assert!(color.style.formats.is_empty());
+        assert_eq!(
+            color.removed,
+            FormatFlags::BOLD | FormatFlags::UNDERLINE | FormatFlags::ITALIC
+        );
+        // A later word turning an attribute back on wins.
+        let color = parse("nobold bold");
+        assert_eq!(
+            (color.style, color.removed),
+            (Style::new().bold(), FormatFlags::empty())
+        );
+    }
+
+    #[test]
+    fn apply_to() {
+        let base = Red.bold().underline().on(Blue);
+        assert_eq!(parse("normal").apply_to(base), base);
+        assert_eq!(
+            parse("green nobold").apply_to(base),
+            Green.underline().on(Blue)
+        );
+        assert_eq!(
+            parse("normal default").apply_to(base),
+            Red.bold().underline().on(Default)
+        );
+        assert_eq!(
+            parse("reset italic").apply_to(base).formats,
+            FormatFlags::ITALIC
+        );
+        assert_eq!(parse("reset italic").apply_to(base).is_fg(), None);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |s: &str| s.parse::<GitColor>().unwrap_err();
+
+        let e = error("bold sparkly");
+        assert_eq!(
+            (e.token.as_str(), e.range, e.kind),
+            ("sparkly", 5..12, ParseStyleErrorKind::UnknownWord)
+        );
+
+        let e = error("red blue green");
+        assert_eq!(
+            (e.token.as_str(), e.range, e.kind),
+            ("green", 9..14, ParseStyleErrorKind::DuplicateColor)
+        );
+
+        assert_eq!(error("256").kind, ParseStyleErrorKind::InvalidColor);
+        assert_eq!(error("#ff88").kind, ParseStyleErrorKind::InvalidColor);
+        assert_eq!(error("nohidden").kind, ParseStyleErrorKind::UnknownWord);
+    }
+
+    #[test]
+    fn round_trip() {
+        for s in [
+            "",
+            "red",
+            "normal blue nobold",
+            "reset brightmagenta default bold ul nostrike",
+            "236 #ff8800 dim italic blink reverse strike",
+        ] {
+            assert_eq!(parse(s).to_string(), s);
+        }
+        assert_eq!(GitColor::from(Purple.hidden()).to_string(), "magenta");
+        assert_eq!(
+            GitColor::from(LightPurple.as_fg()).to_string(),
+            "brightmagenta"
+        );
+    }
+}
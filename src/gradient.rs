@@ -1,4 +1,4 @@
-use crate::{rgb::Rgb, Color};
+use crate::{rgb::Rgb, Color, ColorDepth};
 
 /// Linear color gradient between two color stops
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -46,44 +46,57 @@ impl Gradient {
     }
 
     /// Creates a string with the given `text` wrapped in ANSI escape codes that
-    /// represent a color gradient.
+    /// represent a color gradient. Colors are downgraded to the global
+    /// [`ColorDepth`].
     pub fn build(&self, text: &str, target: TargetGround) -> String {
-        let delta = 1.0 / text.len() as f32;
-        let mut result = text.char_indices().fold(String::new(), |mut acc, (i, c)| {
-            let temp = format!(
-                "\x1B[{}m{}",
-                self.at(i as f32 * delta).ansi_color_code(target),
-                c
-            );
-            acc.push_str(&temp);
-            acc
-        });
+        self.build_with_depth(text, target, ColorDepth::global())
+    }
 
-        result.push_str("\x1B[0m");
-        result
+    /// Creates a string with the given `text` wrapped in ANSI escape codes that
+    /// represent a color gradient, with colors downgraded to the given `depth`.
+    pub fn build_with_depth(&self, text: &str, target: TargetGround, depth: ColorDepth) -> String {
+        let delta = 1.0 / text.len() as f32;
+        gradient_text(text, depth, |i| {
+            let color = Color::from(self.at(i as f32 * delta)).downgrade(depth);
+            color.map(|c| c.ansi_color_code(target))
+        })
     }
 }
 
-///
+/// Creates a string with the given `text` wrapped in ANSI escape codes that
+/// represent both a foreground and a background color gradient. Colors are
+/// downgraded to the global [`ColorDepth`].
 pub fn build_all_gradient_text(text: &str, foreground: Gradient, background: Gradient) -> String {
     let delta = 1.0 / text.len() as f32;
-    let mut result = text.char_indices().fold(String::new(), |mut acc, (i, c)| {
+    let depth = ColorDepth::global();
+    gradient_text(text, depth, |i| {
         let step = i as f32 * delta;
-        let temp = format!(
-            "\x1B[{};{}m{}",
-            foreground
-                .at(step)
-                .ansi_color_code(TargetGround::Foreground),
-            background
-                .at(step)
-                .ansi_color_code(TargetGround::Background),
-            c
-        );
-        acc.push_str(&temp);
+        let fg = Color::from(foreground.at(step)).downgrade(depth);
+        let bg = Color::from(background.at(step)).downgrade(depth);
+        fg.zip(bg).map(|(fg, bg)| {
+            format!(
+                "{};{}",
+                fg.ansi_color_code(TargetGround::Foreground),
+                bg.ansi_color_code(TargetGround::Background)
+            )
+        })
+    })
+}
+
+/// Write each character of `text` preceded by the color codes produced for its
+/// (byte) position, if any.
+fn gradient_text(text: &str, depth: ColorDepth, codes: impl Fn(usize) -> Option<String>) -> String {
+    let mut result = text.char_indices().fold(String::new(), |mut acc, (i, c)| {
+        if let Some(codes) = codes(i) {
+            acc.push_str(&format!("\x1B[{}m", codes));
+        }
+        acc.push(c);
         acc
     });
 
-    result.push_str("\x1B[0m");
+    if depth != ColorDepth::None {
+        result.push_str("\x1B[0m");
+    }
     result
 }
 
@@ -112,3 +125,25 @@ pub trait ANSIColorCode {
     /// Get the ANSI color code associated with this item.
     fn ansi_color_code(&self, target: TargetGround) -> String;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_with_depth() {
+        let gradient = Gradient::new(Rgb::new(255, 0, 0), Rgb::new(0, 0, 255));
+        assert_eq!(
+            gradient.build_with_depth("ab", TargetGround::Foreground, ColorDepth::TrueColor),
+            "\x1B[38;2;255;0;0ma\x1B[38;2;127;0;127mb\x1B[0m"
+        );
+        assert_eq!(
+            gradient.build_with_depth("ab", TargetGround::Background, ColorDepth::Ansi256),
+            "\x1B[48;5;196ma\x1B[48;5;90mb\x1B[0m"
+        );
+        assert_eq!(
+            gradient.build_with_depth("ab", TargetGround::Foreground, ColorDepth::None),
+            "ab"
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use std::fmt::Write;
+
+use crate::display::{AnsiString, AnsiStrings};
+use crate::utils::runs;
+use crate::{Color, Palette, Rgb, Style};
+
+/// How [`HtmlRenderer`] describes styles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HtmlMode {
+    /// Inline `style` attributes, such as `style="color:#cd0000"`, so the
+    /// output needs no stylesheet.
+    #[default]
+    Inline,
+    /// Class names, such as `class="ansi-fg-1 ansi-bold"`, to be styled by a
+    /// stylesheet (see [`HtmlRenderer::stylesheet`]). 24-bit colors have no
+    /// class, so are still given inline.
+    Classes,
+}
+
+/// Renders ANSI strings as HTML, with a `<span>` for each run of differently
+/// styled text, and an `<a>` for each hyperlink.
+///
+/// Text and attributes are HTML-escaped. Hyperlinks are only kept for `http`,
+/// `https`, `ftp`, `mailto` and `file` URLs (or relative ones), so that
+/// untrusted output cannot run scripts; other links are rendered as their
+/// plain text. Window titles are left out.
+///
+/// # Examples
+///
+/// ```
+/// use procr_ansi_term::{AnsiStrings, HtmlMode, HtmlRenderer};
+/// use procr_ansi_term::Color::{Green, Red};
+///
+/// let strings = AnsiStrings([
+///     Red.bold().paint("error"),
+///     Green.paint(" <main.rs>").hyperlink("https://example.com/?a=1&b=2"),
+/// ]);
+///
+/// assert_eq!(
+///     HtmlRenderer::new().render(&strings),
+///     "<span style=\"color:#cd0000;font-weight:bold\">error</span>\
+///      <a href=\"https://example.com/?a=1&amp;b=2\">\
+///      <span style=\"color:#00cd00\"> &lt;main.rs&gt;</span></a>",
+/// );
+/// assert_eq!(
+///     HtmlRenderer::new().with_mode(HtmlMode::Classes).render(&strings),
+///     "<span class=\"ansi-fg-1 ansi-bold\">error</span>\
+///      <a href=\"https://example.com/?a=1&amp;b=2\">\
+///      <span class=\"ansi-fg-2\"> &lt;main.rs&gt;</span></a>",
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HtmlRenderer {
+    mode: HtmlMode,
+    palette: Palette,
+}
+
+/// A format flag, as the method checking whether it is set, along with the
+/// class name and CSS declaration used for it.
+type Format = (fn(&Style) -> bool, &'static str, &'static str);
+
+/// The format flags which have a class and declaration of their own.
+const FORMATS: [Format; 6] = [
+    (Style::is_bold, "bold", "font-weight:bold"),
+    (Style::is_dimmed, "dimmed", "opacity:0.5"),
+    (Style::is_italic, "italic", "font-style:italic"),
+    (
+        Style::is_blink,
+        "blink",
+        "animation:ansi-blink 1s steps(1) infinite",
+    ),
+    (Style::is_hidden, "hidden", "visibility:hidden"),
+    (
+        Style::is_underline,
+        "underline",
+        "text-decoration-line:underline",
+    ),
+];
+
+/// Write text, escaping the characters which are special in HTML.
+pub(crate) fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Whether a URL is safe to link to from a page, which rules out schemes
+/// such as `javascript:`.
+pub(crate) fn is_safe_url(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => matches!(
+            scheme.to_ascii_lowercase().as_str(),
+            "http" | "https" | "ftp" | "mailto" | "file"
+        ),
+        _ => true,
+    }
+}
+
+/// Format a color as a CSS hex color.
+pub(crate) fn hex(rgb: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.r, rgb.g, rgb.b)
+}
+
+impl HtmlRenderer {
+    /// A renderer using inline styles and the xterm palette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given way of describing styles.
+    pub fn with_mode(mut self, mode: HtmlMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Use the given palette for the named and [`Color::Fixed`] colors, and
+    /// for default colors which have to be given explicitly (when reversed).
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// The way this renderer describes styles.
+    pub const fn mode(&self) -> HtmlMode {
+        self.mode
+    }
+
+    /// The palette used by this renderer.
+    pub const fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Render a single ANSI string as HTML.
+    pub fn render_string(&self, s: &AnsiString) -> String {
+        self.render(&AnsiStrings::from(s.clone()))
+    }
+
+    /// Render a sequence of ANSI strings as HTML.
+    ///
+    /// Each run of text which the ANSI output starts with a new style gets its
+    /// own `<span>`, so both break the text up in the same places.
+    pub fn render(&self, strs: &AnsiStrings) -> String {
+        let mut out = String::new();
+        let mut open_link: Option<String> = None;
+
+        for run in runs(strs) {
+            let url = run.url().filter(|url| is_safe_url(url));
+            if url != open_link {
+                if open_link.is_some() {
+                    out.push_str("</a>");
+                }
+                if let Some(url) = &url {
+                    out.push_str("<a href=\"");
+                    escape_html(&mut out, url);
+                    out.push_str("\">");
+                }
+                open_link = url;
+            }
+
+            let attributes = self.attributes(run.style);
+            if attributes.is_empty() {
+                escape_html(&mut out, &run.text);
+            } else {
+                let _ = write!(out, "<span{}>", attributes);
+                escape_html(&mut out, &run.text);
+                out.push_str("</span>");
+            }
+        }
+
+        if open_link.is_some() {
+            out.push_str("</a>");
+        }
+        out
+    }
+
+    /// The attributes of the `<span>` for the given style, which are empty if
+    /// no `<span>` is needed.
+    fn attributes(&self, style: Style) -> String {
+        let mut classes = Vec::new();
+        let mut declarations = Vec::new();
+
+        let (fg, bg) = if style.is_reverse() {
+            // Default colors have to be given explicitly once swapped.
+            let explicit = |color: Option<Color>, default: Rgb| match color {
+                None | Some(Color::Default) => Some(Color::from(default)),
+                color => color,
+            };
+            (
+                explicit(style.is_bg(), self.palette.background),
+                explicit(style.is_fg(), self.palette.foreground),
+            )
+        } else {
+            (style.is_fg(), style.is_bg())
+        };
+        for (color, ground, property) in [(fg, "fg", "color"), (bg, "bg", "background-color")] {
+            let Some(color) = color else { continue };
+            match (self.mode, color.index()) {
+                (HtmlMode::Classes, Some(ix)) => classes.push(format!("ansi-{}-{}", ground, ix)),
+                _ => {
+                    if let Some(rgb) = self.palette.rgb(color) {
+                        declarations.push(format!("{}:{}", property, hex(rgb)));
+                    }
+                }
+            }
+        }
+
+        let mut decorations = Vec::new();
+        for (is_set, class, declaration) in FORMATS {
+            if !is_set(&style) {
+                continue;
+            }
+            match (self.mode, declaration.strip_prefix("text-decoration-line:")) {
+                (HtmlMode::Classes, _) => classes.push(format!("ansi-{}", class)),
+                (HtmlMode::Inline, Some(line)) => decorations.push(line),
+                (HtmlMode::Inline, None) => declarations.push(declaration.to_string()),
+            }
+        }
+        if style.is_strikethrough() {
+            match self.mode {
+                HtmlMode::Classes => classes.push("ansi-strikethrough".to_string()),
+                HtmlMode::Inline => decorations.push("line-through"),
+            }
+        }
+        if !decorations.is_empty() {
+            declarations.push(format!("text-decoration-line:{}", decorations.join(" ")));
+        }
+
+        let mut attributes = String::new();
+        if !classes.is_empty() {
+            let _ = write!(attributes, " class=\"{}\"", classes.join(" "));
+        }
+        if !declarations.is_empty() {
+            attributes.push_str(" style=\"");
+            escape_html(&mut attributes, &declarations.join(";"));
+            attributes.push('"');
+        }
+        attributes
+    }
+
+    /// A stylesheet defining the classes used in [`HtmlMode::Classes`], using
+    /// this renderer's palette.
+    pub fn stylesheet(&self) -> String {
+        let mut css = String::new();
+        for (ix, rgb) in self.palette.colors.iter().enumerate() {
+            let _ = writeln!(css, ".ansi-fg-{} {{ color: {}; }}", ix, hex(*rgb));
+            let _ = writeln!(
+                css,
+                ".ansi-bg-{} {{ background-color: {}; }}",
+                ix,
+                hex(*rgb)
+            );
+        }
+        for (_, class, declaration) in FORMATS {
+            let _ = writeln!(
+                css,
+                ".ansi-{} {{ {}; }}",
+                class,
+                declaration.replace(':', ": ")
+            );
+        }
+        css.push_str(".ansi-strikethrough { text-decoration-line: line-through; }\n");
+        css.push_str(
+            ".ansi-underline.ansi-strikethrough { text-decoration-line: underline line-through; }\n",
+        );
+        css.push_str("@keyframes ansi-blink { 50% { visibility: hidden; } }\n");
+        css
+    }
+}
+
+impl<'a> AnsiString<'a> {
+    /// Render this string as HTML, with inline styles (see [`HtmlRenderer`]).
+    pub fn to_html(&self) -> String {
+        HtmlRenderer::new().render_string(self)
+    }
+}
+
+impl<'a> AnsiStrings<'a> {
+    /// Render these strings as HTML, with inline styles (see
+    /// [`HtmlRenderer`]).
+    pub fn to_html(&self) -> String {
+        HtmlRenderer::new().render(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::AnsiGenericString;
+    use crate::Color::{Blue, Fixed, Green, Red};
+
+    #[test]
+    fn escaping() {
+        assert_eq!(
+            Style::new().paint("<b>\"&'</b>").to_html(),
+            "&lt;b&gt;&quot;&amp;&#39;&lt;/b&gt;"
+        );
+        assert_eq!(
+            Red.paint("x").hyperlink("javascript:alert(1)").to_html(),
+            "<span style=\"color:#cd0000\">x</span>"
+        );
+        assert_eq!(
+            Style::new().paint("x").hyperlink("docs/a:b.html").to_html(),
+            "<a href=\"docs/a:b.html\">x</a>"
+        );
+    }
+
+    #[test]
+    fn inline_styles() {
+        assert_eq!(
+            Fixed(208)
+                .on_bg(Color::Rgb(1, 2, 3))
+                .underline()
+                .strikethrough()
+                .italic()
+                .paint("x")
+                .to_html(),
+            "<span style=\"color:#ff8700;background-color:#010203;font-style:italic;\
+             text-decoration-line:underline line-through\">x</span>"
+        );
+        assert_eq!(
+            Style::new().reverse().fg(Blue).paint("x").to_html(),
+            "<span style=\"color:#000000;background-color:#0000ee\">x</span>"
+        );
+        assert_eq!(Style::new().fg(Color::Default).paint("x").to_html(), "x");
+    }
+
+    #[test]
+    fn runs_and_links() {
+        let url = "https://example.com";
+        let strings = AnsiStrings([
+            AnsiGenericString::title("title"),
+            Red.paint("a"),
+            Red.paint(AnsiStrings([Style::new().paint("b")])),
+            Blue.paint("c").hyperlink(url),
+            Green.paint("d").hyperlink(url),
+            Style::new().paint("e"),
+        ]);
+        let palette = Palette::xterm().with_color(Green, Rgb::from_hex(0x859900));
+        let html = HtmlRenderer::new()
+            .with_mode(HtmlMode::Classes)
+            .with_palette(palette)
+            .render(&strings);
+        assert_eq!(
+            html,
+            "<span class=\"ansi-fg-1\">ab</span>\
+             <a href=\"https://example.com\"><span class=\"ansi-fg-4\">c</span>\
+             <span class=\"ansi-fg-2\">d</span></a>e"
+        );
+    }
+
+    #[test]
+    fn runs_follow_deltas() {
+        let strings = AnsiStrings([Red.bold().paint("a"), Style::new().paint("b")])
+            .rebase_on(Style::new().bold());
+        assert_eq!(strings.to_string(), "\x1B[1;31ma\x1B[0m\x1B[1mb\x1B[0m");
+        assert_eq!(
+            strings.to_html(),
+            "<span style=\"color:#cd0000;font-weight:bold\">a</span>\
+             <span style=\"font-weight:bold\">b</span>"
+        );
+    }
+
+    #[test]
+    fn stylesheet() {
+        let palette = Palette::xterm().with_color(Green, Rgb::from_hex(0x859900));
+        let css = HtmlRenderer::new()
+            .with_mode(HtmlMode::Classes)
+            .with_palette(palette)
+            .stylesheet();
+        assert!(css.contains(".ansi-fg-2 { color: #859900; }"));
+        assert!(css.contains(".ansi-bg-255 { background-color: #eeeeee; }"));
+        assert!(css.contains(".ansi-bold { font-weight: bold; }"));
+    }
+}
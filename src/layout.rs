@@ -0,0 +1,581 @@
+use std::ops::Range;
+
+use crate::display::{AnsiString, AnsiStrings};
+use crate::utils::{fragments, sub_string_by, Fragment, Unit};
+use crate::width::{char_width, grapheme_width, graphemes, str_width};
+use crate::Style;
+
+/// The style given to the padding added by methods such as
+/// [`AnsiGenericStrings::pad_left`](crate::AnsiGenericStrings::pad_left).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PadStyle {
+    /// Use the style of the text next to the padding, so that (for example)
+    /// a background color extends across the whole width.
+    #[default]
+    Surrounding,
+    /// Use the given style.
+    Given(Style),
+}
+
+/// What to fill the padding added by methods such as
+/// [`AnsiGenericStrings::pad_left`](crate::AnsiGenericStrings::pad_left) with.
+///
+/// A `char` fills with that character in the surrounding style, and a
+/// `(char, Style)` pair fills with that character in the given style.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Padding {
+    /// The character to repeat. If it is not one column wide, any columns it
+    /// cannot fill exactly are filled with spaces.
+    pub fill: char,
+    /// The style of the padding.
+    pub style: PadStyle,
+}
+
+impl Default for Padding {
+    fn default() -> Self {
+        Self::from(' ')
+    }
+}
+
+impl From<char> for Padding {
+    fn from(fill: char) -> Self {
+        Self {
+            fill,
+            style: PadStyle::Surrounding,
+        }
+    }
+}
+
+impl From<(char, Style)> for Padding {
+    fn from((fill, style): (char, Style)) -> Self {
+        Self {
+            fill,
+            style: PadStyle::Given(style),
+        }
+    }
+}
+
+impl Padding {
+    /// Produce `columns` columns of padding, styled like `surrounding` unless
+    /// a style was given.
+    fn paint<'a>(self, columns: usize, surrounding: Style) -> AnsiString<'a> {
+        let fill_width = char_width(self.fill);
+        let fills = columns.checked_div(fill_width).unwrap_or(0);
+        let spaces = columns - fills * fill_width;
+
+        let mut text = String::with_capacity(fills * self.fill.len_utf8() + spaces);
+        text.extend(std::iter::repeat_n(self.fill, fills));
+        text.extend(std::iter::repeat_n(' ', spaces));
+
+        let style = match self.style {
+            PadStyle::Surrounding => surrounding,
+            PadStyle::Given(style) => style,
+        };
+        style.paint(text)
+    }
+}
+
+/// The fragments of text which are actually displayed, skipping empty ones
+/// and titles.
+fn displayed<'a>(strs: &AnsiStrings<'a>) -> impl Iterator<Item = Fragment<'a>> {
+    fragments(strs.iter())
+        .into_iter()
+        .filter(|fragment| fragment.is_displayed() && !fragment.text.is_empty())
+}
+
+/// The style of the text displayed in the given column, or of the last text
+/// if it does not reach that far.
+fn style_at(strs: &AnsiStrings, column: usize) -> Style {
+    let mut end = 0;
+    let mut style = Style::new();
+    for fragment in displayed(strs) {
+        end += str_width(&fragment.text);
+        style = fragment.style;
+        if end > column {
+            break;
+        }
+    }
+    style
+}
+
+/// The longest prefix of `s` which fits in `width` columns.
+fn take_columns(s: &str, width: usize) -> &str {
+    let mut used = 0;
+    let mut end = 0;
+    for grapheme in graphemes(s) {
+        used += grapheme_width(grapheme);
+        if used > width {
+            break;
+        }
+        end += grapheme.len();
+    }
+    &s[..end]
+}
+
+impl<'a> AnsiStrings<'a> {
+    /// Shorten these strings so that they take up at most `width` columns,
+    /// ending with the given `ellipsis` if anything had to be cut off.
+    ///
+    /// The ellipsis takes the style of the text it replaces. Characters are
+    /// never split, so the result may be a column short when a wide character
+    /// straddles the cut.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::AnsiStrings;
+    /// use procr_ansi_term::Color::{Blue, Red};
+    ///
+    /// let strings = AnsiStrings([Red.paint("styled "), Blue.paint("strings")]);
+    /// assert_eq!(
+    ///     strings.truncate(10, "…").to_string(),
+    ///     AnsiStrings([Red.paint("styled "), Blue.paint("st"), Blue.paint("…")]).to_string(),
+    /// );
+    /// ```
+    pub fn truncate(&self, width: usize, ellipsis: &str) -> AnsiStrings<'a> {
+        if self.display_width() <= width {
+            return self.clone();
+        }
+
+        let ellipsis = take_columns(ellipsis, width);
+        let keep = width - str_width(ellipsis);
+        let mut truncated: Vec<_> = sub_string_by(Unit::Columns, 0, keep, self)
+            .iter()
+            .cloned()
+            .collect();
+        if !ellipsis.is_empty() {
+            truncated.push(style_at(self, keep).paint(ellipsis.to_owned()));
+        }
+        AnsiStrings(truncated)
+    }
+
+    /// Add padding before these strings, so that they take up at least
+    /// `width` columns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::{AnsiStrings, Style};
+    /// use procr_ansi_term::Color::{Blue, Red};
+    ///
+    /// let strings = AnsiStrings([Red.on_bg(Blue).paint("42")]);
+    /// assert_eq!(
+    ///     strings.pad_left(4, '0').to_string(),
+    ///     AnsiStrings([Red.on_bg(Blue).paint("00"), Red.on_bg(Blue).paint("42")]).to_string(),
+    /// );
+    /// assert_eq!(
+    ///     strings.pad_left(4, (' ', Style::new())).to_string(),
+    ///     AnsiStrings([Style::new().paint("  "), Red.on_bg(Blue).paint("42")]).to_string(),
+    /// );
+    /// ```
+    pub fn pad_left(&self, width: usize, fill: impl Into<Padding>) -> AnsiStrings<'a> {
+        self.pad(width.saturating_sub(self.display_width()), 0, fill.into())
+    }
+
+    /// Add padding after these strings, so that they take up at least `width`
+    /// columns (see [`pad_left`](Self::pad_left)).
+    pub fn pad_right(&self, width: usize, fill: impl Into<Padding>) -> AnsiStrings<'a> {
+        self.pad(0, width.saturating_sub(self.display_width()), fill.into())
+    }
+
+    /// Add padding on both sides of these strings, so that they take up at
+    /// least `width` columns (see [`pad_left`](Self::pad_left)). When the
+    /// padding cannot be split evenly, the extra column goes on the right.
+    pub fn center(&self, width: usize, fill: impl Into<Padding>) -> AnsiStrings<'a> {
+        let extra = width.saturating_sub(self.display_width());
+        self.pad(extra / 2, extra - extra / 2, fill.into())
+    }
+
+    fn pad(&self, left: usize, right: usize, fill: Padding) -> AnsiStrings<'a> {
+        if left == 0 && right == 0 {
+            return self.clone();
+        }
+
+        let first = displayed(self).next().map(|f| f.style).unwrap_or_default();
+        let last = displayed(self).last().map(|f| f.style).unwrap_or_default();
+
+        let mut padded = Vec::with_capacity(self.iter().count() + 2);
+        if left > 0 {
+            padded.push(fill.paint(left, first));
+        }
+        padded.extend(self.iter().cloned());
+        if right > 0 {
+            padded.push(fill.paint(right, last));
+        }
+        AnsiStrings(padded)
+    }
+}
+
+impl<'a> AnsiString<'a> {
+    /// Shorten this string so that it takes up at most `width` columns (see
+    /// [`AnsiGenericStrings::truncate`](crate::AnsiGenericStrings::truncate)).
+    pub fn truncate(&self, width: usize, ellipsis: &str) -> AnsiStrings<'a> {
+        AnsiStrings([self.clone()]).truncate(width, ellipsis)
+    }
+
+    /// Add padding before this string, so that it takes up at least `width`
+    /// columns (see [`AnsiGenericStrings::pad_left`](crate::AnsiGenericStrings::pad_left)).
+    pub fn pad_left(&self, width: usize, fill: impl Into<Padding>) -> AnsiStrings<'a> {
+        AnsiStrings([self.clone()]).pad_left(width, fill)
+    }
+
+    /// Add padding after this string, so that it takes up at least `width`
+    /// columns (see [`AnsiGenericStrings::pad_right`](crate::AnsiGenericStrings::pad_right)).
+    pub fn pad_right(&self, width: usize, fill: impl Into<Padding>) -> AnsiStrings<'a> {
+        AnsiStrings([self.clone()]).pad_right(width, fill)
+    }
+
+    /// Add padding on both sides of this string, so that it takes up at least
+    /// `width` columns (see [`AnsiGenericStrings::center`](crate::AnsiGenericStrings::center)).
+    pub fn center(&self, width: usize, fill: impl Into<Padding>) -> AnsiStrings<'a> {
+        AnsiStrings([self.clone()]).center(width, fill)
+    }
+}
+
+/// A grapheme cluster of some fragment of text, being laid out by
+/// [`AnsiGenericStrings::wrap`](crate::AnsiGenericStrings::wrap).
+struct Piece {
+    fragment: usize,
+    bytes: Range<usize>,
+    width: usize,
+}
+
+/// Greedily fills lines with words, as they are read.
+struct Wrapper {
+    width: usize,
+    lines: Vec<Vec<Piece>>,
+    line: Vec<Piece>,
+    line_width: usize,
+    /// Whether the current line was started by wrapping, rather than at the
+    /// start of the text or after a newline.
+    wrapped: bool,
+    /// The word being read.
+    word: Vec<Piece>,
+    /// The whitespace read since the last word.
+    space: Vec<Piece>,
+}
+
+impl Wrapper {
+    fn break_line(&mut self, wrapped: bool) {
+        self.lines.push(std::mem::take(&mut self.line));
+        self.line_width = 0;
+        self.wrapped = wrapped;
+    }
+
+    /// Add the word which was being read (along with any whitespace before
+    /// it) to the current line, or start a new line for it if it does not fit.
+    fn end_word(&mut self) {
+        if self.word.is_empty() {
+            return;
+        }
+        let mut pieces = std::mem::take(&mut self.word);
+        let space = std::mem::take(&mut self.space);
+        let width = |pieces: &[Piece]| pieces.iter().map(|p| p.width).sum::<usize>();
+
+        if !self.line.is_empty() && self.line_width + width(&space) + width(&pieces) > self.width {
+            // Whitespace at a line break is dropped.
+            self.break_line(true);
+        } else if !(self.line.is_empty() && self.wrapped) {
+            pieces.splice(0..0, space);
+        }
+
+        // Words longer than a line are broken wherever they have to be.
+        for piece in pieces {
+            if !self.line.is_empty() && self.line_width + piece.width > self.width {
+                self.break_line(true);
+            }
+            self.line_width += piece.width;
+            self.line.push(piece);
+        }
+    }
+}
+
+impl<'a> AnsiStrings<'a> {
+    /// Split these strings into lines of at most `width` columns, breaking
+    /// at whitespace where possible, and within words where they are too long
+    /// to fit on a line of their own.
+    ///
+    /// Each line is a separate sequence, which opens the styles it uses and
+    /// ends with a reset, so that lines can be printed independently.
+    /// Hyperlinks which are split across lines link from every line, and are
+    /// only highlighted as one link if they have an id (see
+    /// [`with_link_ids`](crate::utils::with_link_ids)). Newlines in the text
+    /// always start a new line, and whitespace at the start of a line is only
+    /// kept if the line is not the result of wrapping.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::AnsiStrings;
+    /// use procr_ansi_term::Color::{Blue, Red};
+    ///
+    /// let message = AnsiStrings([Red.paint("error: "), Blue.bold().paint("file not found")]);
+    /// let lines: Vec<String> = message.wrap(12).iter().map(|line| line.to_string()).collect();
+    /// assert_eq!(
+    ///     lines,
+    ///     [
+    ///         AnsiStrings([Red.paint("error: "), Blue.bold().paint("file")]).to_string(),
+    ///         Blue.bold().paint("not found").to_string(),
+    ///     ]
+    /// );
+    /// ```
+    pub fn wrap(&self, width: usize) -> Vec<AnsiStrings<'a>> {
+        let fragments: Vec<Fragment<'a>> = fragments(self.iter())
+            .into_iter()
+            .filter(Fragment::is_displayed)
+            .collect();
+
+        let mut wrapper = Wrapper {
+            width,
+            lines: Vec::new(),
+            line: Vec::new(),
+            line_width: 0,
+            wrapped: false,
+            word: Vec::new(),
+            space: Vec::new(),
+        };
+        for (ix, fragment) in fragments.iter().enumerate() {
+            let mut offset = 0;
+            for grapheme in graphemes(&fragment.text) {
+                let piece = Piece {
+                    fragment: ix,
+                    bytes: offset..offset + grapheme.len(),
+                    width: grapheme_width(grapheme),
+                };
+                offset = piece.bytes.end;
+
+                if grapheme == "\n" || grapheme == "\r\n" {
+                    wrapper.end_word();
+                    wrapper.space.clear();
+                    wrapper.break_line(false);
+                } else if grapheme.chars().all(char::is_whitespace) {
+                    wrapper.end_word();
+                    wrapper.space.push(piece);
+                } else {
+                    wrapper.word.push(piece);
+                }
+            }
+        }
+        wrapper.end_word();
+        if !wrapper.line.is_empty() {
+            wrapper.break_line(false);
+        }
+
+        wrapper
+            .lines
+            .into_iter()
+            .map(|line| {
+                // Join up pieces of the same fragment again.
+                let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+                for piece in line {
+                    match runs.last_mut() {
+                        Some((fragment, bytes))
+                            if *fragment == piece.fragment && bytes.end == piece.bytes.start =>
+                        {
+                            bytes.end = piece.bytes.end;
+                        }
+                        _ => runs.push((piece.fragment, piece.bytes)),
+                    }
+                }
+                AnsiStrings(
+                    runs.into_iter()
+                        .map(|(fragment, bytes)| fragments[fragment].slice(bytes)),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Color::*;
+
+    fn assert_same(actual: AnsiStrings, expected: AnsiStrings) {
+        assert_eq!(actual.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn truncate() {
+        let cell = AnsiStrings([Red.paint("日本"), Blue.bold().paint("語です")]);
+        assert_eq!(cell.display_width(), 10);
+
+        assert_same(cell.truncate(10, "…"), cell.clone());
+        assert_same(
+            cell.truncate(5, "…"),
+            AnsiStrings([Red.paint("日本"), Blue.bold().paint("…")]),
+        );
+        // The second wide character would straddle the cut.
+        assert_same(
+            cell.truncate(6, "..."),
+            AnsiStrings([Red.paint("日"), Red.paint("...")]),
+        );
+        assert_same(cell.truncate(2, "..."), AnsiStrings([Red.paint("..")]));
+        assert_same(cell.truncate(0, "…"), AnsiStrings([]));
+        assert_same(
+            Green.paint("abcdef").truncate(4, ""),
+            AnsiStrings([Green.paint("abcd")]),
+        );
+    }
+
+    #[test]
+    fn truncate_keeps_links() {
+        let link = Cyan.paint("documentation").hyperlink("https://example.com");
+        assert_same(
+            link.truncate(5, "…"),
+            AnsiStrings([
+                Cyan.paint("docu").hyperlink("https://example.com"),
+                Cyan.paint("…"),
+            ]),
+        );
+    }
+
+    #[test]
+    fn padding() {
+        let cell = AnsiStrings([Red.on_bg(White).paint("ab"), Blue.on_bg(Black).paint("c")]);
+
+        assert_same(
+            cell.pad_right(5, ' '),
+            AnsiStrings([
+                Red.on_bg(White).paint("ab"),
+                Blue.on_bg(Black).paint("c"),
+                Blue.on_bg(Black).paint("  "),
+            ]),
+        );
+        assert_same(
+            cell.center(6, '-'),
+            AnsiStrings([
+                Red.on_bg(White).paint("-"),
+                Red.on_bg(White).paint("ab"),
+                Blue.on_bg(Black).paint("c"),
+                Blue.on_bg(Black).paint("--"),
+            ]),
+        );
+        assert_same(
+            cell.pad_left(6, ('・', Style::new().dimmed())),
+            AnsiStrings([
+                Style::new().dimmed().paint("・ "),
+                Red.on_bg(White).paint("ab"),
+                Blue.on_bg(Black).paint("c"),
+            ]),
+        );
+        assert_same(cell.pad_left(2, ' '), cell.clone());
+        assert_same(
+            AnsiStrings([]).center(2, ' '),
+            AnsiStrings([Style::new().paint("  ")]),
+        );
+    }
+
+    fn lines(strings: &AnsiStrings, width: usize) -> Vec<String> {
+        strings
+            .wrap(width)
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn wrap() {
+        let text = AnsiStrings([
+            Red.paint("the quick "),
+            Green.bold().paint("brown fox"),
+            Style::new().paint(" jumps"),
+        ]);
+        assert_eq!(
+            lines(&text, 10),
+            [
+                Red.paint("the quick").to_string(),
+                Green.bold().paint("brown fox").to_string(),
+                Style::new().paint("jumps").to_string(),
+            ]
+        );
+        assert_eq!(
+            lines(&text, 15),
+            [
+                AnsiStrings([Red.paint("the quick "), Green.bold().paint("brown")]).to_string(),
+                AnsiStrings([Green.bold().paint("fox"), Style::new().paint(" jumps")]).to_string(),
+            ]
+        );
+        assert_eq!(lines(&AnsiStrings([]), 10), Vec::<String>::new());
+    }
+
+    #[test]
+    fn wrap_lines_end_with_reset() {
+        for line in AnsiStrings([Blue.on_bg(White).paint("one two three")]).wrap(5) {
+            let line = line.to_string();
+            assert!(line.starts_with("\x1B[47;34m"), "{:?}", line);
+            assert!(line.ends_with("\x1B[0m"), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn wrap_hard_breaks() {
+        assert_eq!(
+            lines(&AnsiStrings([Red.paint("a abcdefgh")]), 3),
+            ["a", "abc", "def", "gh"].map(|s| Red.paint(s).to_string())
+        );
+        // Wide characters are not split.
+        assert_eq!(
+            lines(&AnsiStrings([Red.paint("漢字です")]), 5),
+            ["漢字", "です"].map(|s| Red.paint(s).to_string())
+        );
+    }
+
+    #[test]
+    fn wrap_newlines_and_indentation() {
+        let text = AnsiStrings([Red.paint("  first line\n\n    second   line\n")]);
+        assert_eq!(
+            lines(&text, 12),
+            ["  first line", "", "    second", "line"].map(|s| {
+                if s.is_empty() {
+                    String::new()
+                } else {
+                    Red.paint(s).to_string()
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn wrap_keeps_links() {
+        let url = "https://example.com";
+        let text = AnsiStrings([
+            Style::new().paint("see "),
+            Cyan.underline().paint("the manual page").hyperlink(url),
+        ]);
+        assert_eq!(
+            lines(&text, 8),
+            [
+                AnsiStrings([
+                    Style::new().paint("see "),
+                    Cyan.underline().paint("the").hyperlink(url),
+                ])
+                .to_string(),
+                Cyan.underline().paint("manual").hyperlink(url).to_string(),
+                Cyan.underline().paint("page").hyperlink(url).to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_keeps_link_ids() {
+        let url = "https://example.com";
+        let text = AnsiStrings([Cyan
+            .paint("the manual page")
+            .hyperlink(url)
+            .with_link_id("m")]);
+        assert_eq!(
+            lines(&text, 10),
+            [
+                Cyan.paint("the manual")
+                    .hyperlink(url)
+                    .with_link_id("m")
+                    .to_string(),
+                Cyan.paint("page")
+                    .hyperlink(url)
+                    .with_link_id("m")
+                    .to_string(),
+            ]
+        );
+    }
+}
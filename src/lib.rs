@@ -170,7 +170,7 @@
 //! use procr_ansi_term::{AnsiString, AnsiStrings};
 //!
 //! let some_value = format!("{:b}", 42);
-//! let strings: [AnsiString<'static>] = [
+//! let strings: [AnsiString<'static>; 3] = [
 //!     Red.paint("["),
 //!     Red.bold().paint(some_value),
 //!     Red.paint("]"),
@@ -225,9 +225,14 @@ pub mod utils;
 pub use ansi::{Infix, Prefix, Suffix};
 
 mod style;
-pub use style::{Color, Style};
+pub use style::{Color, FormatFlags, Style, UnderlineKind};
+
+/// Reading and writing styles as human-readable strings.
+pub mod style_str;
+pub use style_str::*;
 
 mod difference;
+pub use difference::DeltaStrategy;
 /// Functionality to write an ANSI string to [`AnyWrite`] implementors.
 mod display;
 pub use display::*;
@@ -239,9 +244,12 @@ pub use write::*;
 
 /// Helpers for managing MS Windows related details.
 mod windows;
+#[cfg(windows)]
 pub use crate::windows::*;
 
 /// Helpers for debugging ANSI strings.
+// Only some of these are used outside of tests.
+#[allow(dead_code)]
 mod debug;
 
 /// Helpers for creating color gradients.
@@ -252,5 +260,47 @@ pub use gradient::*;
 mod rgb;
 pub use rgb::*;
 
-pub use procr_ansi_format::ansi_format;
+/// Adapting colors to what a terminal is able to display.
+pub mod color_depth;
+pub use color_depth::*;
+
+/// Detecting what a terminal is able to display.
+pub mod capabilities;
+pub use capabilities::*;
+
+/// Parsing text containing ANSI escape sequences back into styled strings.
+pub mod parse;
+pub use parse::*;
+
+mod unicode_tables;
+
+/// Reading and writing styles in the color syntax of `git config`.
+pub mod git_color;
+pub use git_color::*;
+
+/// Styling file names according to `LS_COLORS`.
+pub mod ls_colors;
+pub use ls_colors::*;
+
+/// Measuring the number of terminal columns taken up by text.
+pub mod width;
+pub use width::*;
+
+/// Fitting styled strings into a given number of terminal columns.
+pub mod layout;
+pub use layout::*;
+
+/// Removing ANSI escape sequences from output.
+pub mod strip;
+pub use strip::*;
+
+/// Rendering styled strings as HTML.
+pub mod html;
+pub use html::*;
+
+/// Rendering styled strings as SVG images of a terminal.
+pub mod svg;
+pub use svg::*;
+
+pub use procr_ansi_format::{ansi_eprintln, ansi_format, ansi_println, ansi_write, ansi_writeln};
 extern crate self as procr_ansi_term;
@@ -0,0 +1,603 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::path::Path;
+
+use crate::parse::decode_sgr;
+use crate::style::Style;
+
+/// The kinds of file that `LS_COLORS` can give a style to, each named by a
+/// two letter code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Indicator {
+    /// `no`: anything without a more specific style.
+    Normal,
+    /// `fi`: a regular file.
+    File,
+    /// `di`: a directory.
+    Directory,
+    /// `ln`: a symbolic link.
+    Symlink,
+    /// `mh`: a regular file with more than one hard link.
+    MultiHardLink,
+    /// `pi`: a named pipe.
+    Fifo,
+    /// `so`: a socket.
+    Socket,
+    /// `do`: a door.
+    Door,
+    /// `bd`: a block device.
+    BlockDevice,
+    /// `cd`: a character device.
+    CharDevice,
+    /// `or`: a symbolic link whose target does not exist.
+    OrphanSymlink,
+    /// `mi`: a file that does not exist (such as an orphan link's target).
+    Missing,
+    /// `su`: a file that is set-user-ID.
+    SetUid,
+    /// `sg`: a file that is set-group-ID.
+    SetGid,
+    /// `ca`: a file with capabilities.
+    Capability,
+    /// `tw`: a directory that is sticky and writable by others.
+    StickyOtherWritable,
+    /// `ow`: a directory that is writable by others, but not sticky.
+    OtherWritable,
+    /// `st`: a directory that is sticky, but not writable by others.
+    Sticky,
+    /// `ex`: an executable file.
+    Executable,
+}
+
+impl Indicator {
+    const ALL: [Indicator; 19] = [
+        Indicator::Normal,
+        Indicator::File,
+        Indicator::Directory,
+        Indicator::Symlink,
+        Indicator::MultiHardLink,
+        Indicator::Fifo,
+        Indicator::Socket,
+        Indicator::Door,
+        Indicator::BlockDevice,
+        Indicator::CharDevice,
+        Indicator::OrphanSymlink,
+        Indicator::Missing,
+        Indicator::SetUid,
+        Indicator::SetGid,
+        Indicator::Capability,
+        Indicator::StickyOtherWritable,
+        Indicator::OtherWritable,
+        Indicator::Sticky,
+        Indicator::Executable,
+    ];
+
+    /// The two letter code naming this indicator in `LS_COLORS`.
+    pub const fn code(self) -> &'static str {
+        match self {
+            Indicator::Normal => "no",
+            Indicator::File => "fi",
+            Indicator::Directory => "di",
+            Indicator::Symlink => "ln",
+            Indicator::MultiHardLink => "mh",
+            Indicator::Fifo => "pi",
+            Indicator::Socket => "so",
+            Indicator::Door => "do",
+            Indicator::BlockDevice => "bd",
+            Indicator::CharDevice => "cd",
+            Indicator::OrphanSymlink => "or",
+            Indicator::Missing => "mi",
+            Indicator::SetUid => "su",
+            Indicator::SetGid => "sg",
+            Indicator::Capability => "ca",
+            Indicator::StickyOtherWritable => "tw",
+            Indicator::OtherWritable => "ow",
+            Indicator::Sticky => "st",
+            Indicator::Executable => "ex",
+        }
+    }
+
+    /// Look up an indicator by its two letter code.
+    pub fn from_code(code: &str) -> Option<Indicator> {
+        Indicator::ALL.into_iter().find(|i| i.code() == code)
+    }
+}
+
+/// Codes which `LS_COLORS` may contain, but which hold raw escape sequences
+/// (or are obsolete) rather than styles: the left, right and end codes, the
+/// reset sequence and the clear-to-end-of-line sequence.
+const NON_STYLE_CODES: [&str; 5] = ["lc", "rc", "ec", "rs", "cl"];
+
+/// What sort of file is being painted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    /// A regular file.
+    File,
+    /// A directory.
+    Directory,
+    /// A symbolic link whose target exists.
+    Symlink,
+    /// A symbolic link whose target does not exist.
+    OrphanSymlink,
+    /// A named pipe.
+    Fifo,
+    /// A socket.
+    Socket,
+    /// A door.
+    Door,
+    /// A block device.
+    BlockDevice,
+    /// A character device.
+    CharDevice,
+    /// A file that does not exist.
+    Missing,
+}
+
+/// The file metadata which decides how `LS_COLORS` paints a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileMeta {
+    /// What sort of file this is.
+    pub kind: FileKind,
+    /// The Unix mode bits (permissions, together with the set-user-ID,
+    /// set-group-ID and sticky bits).
+    pub mode: u32,
+    /// The number of hard links to the file.
+    pub hard_links: u64,
+    /// Whether the file has capabilities.
+    pub capability: bool,
+}
+
+const SET_UID: u32 = 0o4000;
+const SET_GID: u32 = 0o2000;
+const STICKY: u32 = 0o1000;
+const OTHER_WRITABLE: u32 = 0o0002;
+const EXECUTABLE: u32 = 0o0111;
+
+impl FileMeta {
+    /// Metadata for a file of the given kind, with no mode bits set and a
+    /// single hard link.
+    pub const fn new(kind: FileKind) -> Self {
+        FileMeta {
+            kind,
+            mode: 0,
+            hard_links: 1,
+            capability: false,
+        }
+    }
+
+    /// Create a copy of this metadata with the given mode bits.
+    pub const fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Create a copy of this metadata with the given number of hard links.
+    pub const fn with_hard_links(mut self, hard_links: u64) -> Self {
+        self.hard_links = hard_links;
+        self
+    }
+
+    /// Create a copy of this metadata, marking whether the file has
+    /// capabilities.
+    pub const fn with_capability(mut self, capability: bool) -> Self {
+        self.capability = capability;
+        self
+    }
+}
+
+impl From<&Metadata> for FileMeta {
+    /// Take the kind, mode and hard link count from file system metadata.
+    ///
+    /// Metadata of a symbolic link (from [`std::fs::symlink_metadata`]) does
+    /// not say whether its target exists, so orphans need to be marked with
+    /// [`FileKind::OrphanSymlink`] by the caller. Capabilities are never
+    /// detected. Outside Unix, only files, directories and links are told
+    /// apart.
+    fn from(metadata: &Metadata) -> Self {
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_dir() {
+            FileKind::Directory
+        } else if file_type.is_symlink() {
+            FileKind::Symlink
+        } else {
+            FileKind::File
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+            let kind = if file_type.is_fifo() {
+                FileKind::Fifo
+            } else if file_type.is_socket() {
+                FileKind::Socket
+            } else if file_type.is_block_device() {
+                FileKind::BlockDevice
+            } else if file_type.is_char_device() {
+                FileKind::CharDevice
+            } else {
+                kind
+            };
+            FileMeta::new(kind)
+                .with_mode(metadata.mode())
+                .with_hard_links(metadata.nlink())
+        }
+
+        #[cfg(not(unix))]
+        {
+            FileMeta::new(kind)
+        }
+    }
+}
+
+/// Styles for file names, as given by the `LS_COLORS` environment variable
+/// (usually set up with `dircolors`).
+///
+/// ```
+/// use procr_ansi_term::{Color, FileKind, FileMeta, LsColors};
+///
+/// let ls_colors = LsColors::parse("di=01;34:ex=01;32:*.tar=31");
+/// let dir = FileMeta::new(FileKind::Directory);
+/// let exe = FileMeta::new(FileKind::File).with_mode(0o755);
+/// let file = FileMeta::new(FileKind::File);
+///
+/// assert_eq!(ls_colors.style_for("src", &dir), Some(Color::Blue.bold()));
+/// assert_eq!(ls_colors.style_for("run.sh", &exe), Some(Color::Green.bold()));
+/// assert_eq!(ls_colors.style_for("a.tar", &file), Some(Color::Red.as_fg()));
+/// assert_eq!(ls_colors.style_for("a.txt", &file), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LsColors {
+    indicators: HashMap<Indicator, Style>,
+    /// Indicators given as `0` or `00`, which GNU `ls` passes over when
+    /// looking for a more specific indicator than the file type.
+    uncolored: HashSet<Indicator>,
+    /// File name suffixes (from `*suffix` entries), in the order given.
+    suffixes: Vec<(String, Style)>,
+    links_as_target: bool,
+    invalid: Vec<String>,
+}
+
+impl LsColors {
+    /// Parse the `:` separated `key=value` entries of an `LS_COLORS` string.
+    ///
+    /// Values are SGR parameters, such as `01;34` or `38;5;208`, and are
+    /// decoded with [`decode_sgr`]. Entries which cannot be understood are
+    /// skipped (apart from any SGR parameters which were understood) and can
+    /// be found with [`LsColors::invalid_entries`]. Later entries override
+    /// earlier ones, and an empty value leaves that kind of file unstyled.
+    pub fn parse(s: &str) -> Self {
+        let mut ls_colors = LsColors::default();
+
+        for entry in s.split(':').filter(|entry| !entry.is_empty()) {
+            let Some((key, value)) = entry.split_once('=') else {
+                ls_colors.invalid.push(entry.to_string());
+                continue;
+            };
+
+            if key == "ln" && value == "target" {
+                ls_colors.links_as_target = true;
+                ls_colors.indicators.remove(&Indicator::Symlink);
+                continue;
+            }
+            if NON_STYLE_CODES.contains(&key) {
+                continue;
+            }
+
+            let style = if value.is_empty() {
+                None
+            } else {
+                match decode_sgr(Style::new(), value) {
+                    Ok(style) => Some(style),
+                    Err(error) => {
+                        ls_colors.invalid.push(entry.to_string());
+                        Some(error.partial)
+                    }
+                }
+            };
+
+            if let Some(suffix) = key.strip_prefix('*') {
+                ls_colors.suffixes.retain(|(s, _)| s != suffix);
+                if let Some(style) = style {
+                    ls_colors.suffixes.push((suffix.to_string(), style));
+                }
+            } else if let Some(indicator) = Indicator::from_code(key) {
+                if indicator == Indicator::Symlink {
+                    ls_colors.links_as_target = false;
+                }
+                match style {
+                    Some(style) => ls_colors.indicators.insert(indicator, style),
+                    None => ls_colors.indicators.remove(&indicator),
+                };
+                if matches!(value, "0" | "00") {
+                    ls_colors.uncolored.insert(indicator);
+                } else {
+                    ls_colors.uncolored.remove(&indicator);
+                }
+            } else {
+                ls_colors.invalid.push(entry.to_string());
+            }
+        }
+
+        ls_colors
+    }
+
+    /// Parse the `LS_COLORS` environment variable, if it is set.
+    pub fn from_env() -> Option<Self> {
+        std::env::var("LS_COLORS")
+            .ok()
+            .map(|value| LsColors::parse(&value))
+    }
+
+    /// The entries which could not be (completely) understood while parsing.
+    pub fn invalid_entries(&self) -> &[String] {
+        &self.invalid
+    }
+
+    /// Whether symbolic links should be painted like their targets
+    /// (`ln=target`). When this is set, [`LsColors::style_for`] should be
+    /// given the metadata of a link's target rather than of the link itself.
+    pub fn links_as_target(&self) -> bool {
+        self.links_as_target
+    }
+
+    /// The style given to an indicator, if any.
+    pub fn indicator(&self, indicator: Indicator) -> Option<Style> {
+        self.indicators.get(&indicator).copied()
+    }
+
+    /// The style given to a file name suffix. Suffixes which match with the
+    /// same case are preferred, then those which match ignoring ASCII case.
+    /// The longest match wins, and amongst equally long ones the last given.
+    pub fn suffix(&self, file_name: &str) -> Option<Style> {
+        let best = |matches: &dyn Fn(&str) -> bool| {
+            self.suffixes
+                .iter()
+                .filter(|(suffix, _)| matches(suffix))
+                .max_by_key(|(suffix, _)| suffix.len())
+                .map(|(_, style)| *style)
+        };
+        best(&|suffix| file_name.ends_with(suffix)).or_else(|| {
+            best(&|suffix| {
+                file_name.len() >= suffix.len()
+                    && file_name.is_char_boundary(file_name.len() - suffix.len())
+                    && file_name[file_name.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+            })
+        })
+    }
+
+    /// The style to paint a file with, following the rules of GNU `ls`.
+    ///
+    /// The most specific indicator that has a style is used: for example, an
+    /// executable file is painted with `ex` if it is given, and otherwise
+    /// falls back to `fi`. As in `ls`, an indicator such as `ex` or `mh` which
+    /// is given as `0` or `00` counts as not given here. Suffix entries only
+    /// apply to regular files without a more specific indicator. Returns
+    /// `None` when nothing (not even `no`) applies, in which case the file
+    /// should be left unstyled.
+    pub fn style_for(&self, path: impl AsRef<Path>, meta: &FileMeta) -> Option<Style> {
+        let path = path.as_ref();
+        let is_colored = |indicator| {
+            self.indicators.contains_key(&indicator) && !self.uncolored.contains(&indicator)
+        };
+        let mode = meta.mode;
+
+        let indicator = match meta.kind {
+            FileKind::File => {
+                if mode & SET_UID != 0 && is_colored(Indicator::SetUid) {
+                    Indicator::SetUid
+                } else if mode & SET_GID != 0 && is_colored(Indicator::SetGid) {
+                    Indicator::SetGid
+                } else if meta.capability && is_colored(Indicator::Capability) {
+                    Indicator::Capability
+                } else if mode & EXECUTABLE != 0 && is_colored(Indicator::Executable) {
+                    Indicator::Executable
+                } else if meta.hard_links > 1 && is_colored(Indicator::MultiHardLink) {
+                    Indicator::MultiHardLink
+                } else {
+                    let file_name = path.file_name().unwrap_or(path.as_os_str());
+                    if let Some(style) = self.suffix(&file_name.to_string_lossy()) {
+                        return Some(style);
+                    }
+                    Indicator::File
+                }
+            }
+            FileKind::Directory => {
+                let sticky = mode & STICKY != 0;
+                let other_writable = mode & OTHER_WRITABLE != 0;
+                if sticky && other_writable && is_colored(Indicator::StickyOtherWritable) {
+                    Indicator::StickyOtherWritable
+                } else if other_writable && is_colored(Indicator::OtherWritable) {
+                    Indicator::OtherWritable
+                } else if sticky && is_colored(Indicator::Sticky) {
+                    Indicator::Sticky
+                } else {
+                    Indicator::Directory
+                }
+            }
+            FileKind::Symlink => Indicator::Symlink,
+            FileKind::OrphanSymlink if self.indicators.contains_key(&Indicator::OrphanSymlink) => {
+                Indicator::OrphanSymlink
+            }
+            FileKind::OrphanSymlink => Indicator::Symlink,
+            FileKind::Fifo => Indicator::Fifo,
+            FileKind::Socket => Indicator::Socket,
+            FileKind::Door => Indicator::Door,
+            FileKind::BlockDevice => Indicator::BlockDevice,
+            FileKind::CharDevice => Indicator::CharDevice,
+            FileKind::Missing => Indicator::Missing,
+        };
+
+        self.indicator(indicator)
+            .or_else(|| self.indicator(Indicator::Normal))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::style::Color::*;
+
+    const DIRCOLORS: &str = "rs=0:di=01;34:ln=01;36:mh=00:pi=40;33:so=01;35:do=01;35:\
+        bd=40;33;01:cd=40;33;01:or=40;31;01:mi=00:su=37;41:sg=30;43:ca=00:tw=30;42:\
+        ow=34;42:st=37;44:ex=01;32:*.tar=01;31:*.TAR=35:*.jpg=01;35:*~=00;90:";
+
+    fn file(mode: u32) -> FileMeta {
+        FileMeta::new(FileKind::File).with_mode(mode)
+    }
+
+    #[test]
+    fn indicators() {
+        let ls_colors = LsColors::parse(DIRCOLORS);
+        assert!(ls_colors.invalid_entries().is_empty());
+        assert_eq!(ls_colors.indicator(Indicator::Directory), Some(Blue.bold()));
+        assert_eq!(
+            ls_colors.indicator(Indicator::BlockDevice),
+            Some(Yellow.bold().on(Black))
+        );
+        assert_eq!(ls_colors.indicator(Indicator::Missing), Some(Style::new()));
+        assert_eq!(ls_colors.indicator(Indicator::File), None);
+        for indicator in Indicator::ALL {
+            assert_eq!(Indicator::from_code(indicator.code()), Some(indicator));
+        }
+    }
+
+    #[test]
+    fn regular_files() {
+        let ls_colors = LsColors::parse(DIRCOLORS);
+        assert_eq!(ls_colors.style_for("a.txt", &file(0o644)), None);
+        assert_eq!(ls_colors.style_for("run", &file(0o755)), Some(Green.bold()));
+        assert_eq!(
+            ls_colors.style_for("sudo", &file(0o4755)),
+            Some(White.on_bg(Red))
+        );
+        assert_eq!(
+            ls_colors.style_for("write", &file(0o2755)),
+            Some(Black.on_bg(Yellow))
+        );
+        // `mh=00` counts as not given, so the suffix still applies.
+        let linked = file(0o644).with_hard_links(2);
+        assert_eq!(ls_colors.style_for("a.jpg", &linked), Some(Purple.bold()));
+        let custom = LsColors::parse("mh=44:ex=00:*.jpg=35");
+        assert_eq!(
+            custom.style_for("a.jpg", &linked),
+            Some(Style::new().on(Blue))
+        );
+        assert_eq!(
+            custom.style_for("a.jpg", &file(0o755)),
+            Some(Purple.as_fg())
+        );
+        // Suffixes don't apply to executables.
+        assert_eq!(
+            ls_colors.style_for("a.tar", &file(0o755)),
+            Some(Green.bold())
+        );
+    }
+
+    #[test]
+    fn suffixes() {
+        let ls_colors = LsColors::parse(DIRCOLORS);
+        let plain = file(0o644);
+        assert_eq!(ls_colors.style_for("dir/a.tar", &plain), Some(Red.bold()));
+        assert_eq!(ls_colors.style_for("a.TAR", &plain), Some(Purple.as_fg()));
+        // Ties are won by the last suffix given.
+        assert_eq!(ls_colors.style_for("a.Tar", &plain), Some(Purple.as_fg()));
+        assert_eq!(ls_colors.style_for("a.JPG", &plain), Some(Purple.bold()));
+        assert_eq!(
+            ls_colors.style_for("notes~", &plain),
+            Some(DarkGray.as_fg())
+        );
+        // Only the file name is matched.
+        assert_eq!(ls_colors.style_for("a.tar/b", &plain), None);
+
+        let ls_colors = LsColors::parse("*.gz=31:*.tar.gz=32:*.gz=33");
+        assert_eq!(ls_colors.suffix("a.tar.gz"), Some(Green.as_fg()));
+        assert_eq!(ls_colors.suffix("a.gz"), Some(Yellow.as_fg()));
+    }
+
+    #[test]
+    fn directories() {
+        let ls_colors = LsColors::parse(DIRCOLORS);
+        let dir = |mode| FileMeta::new(FileKind::Directory).with_mode(mode);
+        assert_eq!(ls_colors.style_for("d", &dir(0o755)), Some(Blue.bold()));
+        assert_eq!(
+            ls_colors.style_for("tmp", &dir(0o1777)),
+            Some(Black.on_bg(Green))
+        );
+        assert_eq!(
+            ls_colors.style_for("d", &dir(0o777)),
+            Some(Blue.on_bg(Green))
+        );
+        assert_eq!(
+            ls_colors.style_for("d", &dir(0o1755)),
+            Some(White.on_bg(Blue))
+        );
+    }
+
+    #[test]
+    fn other_kinds() {
+        let ls_colors = LsColors::parse(DIRCOLORS);
+        let style = |kind| ls_colors.style_for("x", &FileMeta::new(kind));
+        assert_eq!(style(FileKind::Symlink), Some(Cyan.bold()));
+        assert_eq!(style(FileKind::OrphanSymlink), Some(Red.bold().on(Black)));
+        assert_eq!(style(FileKind::Fifo), Some(Yellow.on_bg(Black)));
+        assert_eq!(style(FileKind::Socket), Some(Purple.bold()));
+
+        // Without `or`, orphans are painted as links, and anything else
+        // without a style falls back to `no`.
+        let ls_colors = LsColors::parse("no=33:ln=36");
+        let style = |kind| ls_colors.style_for("x", &FileMeta::new(kind));
+        assert_eq!(style(FileKind::OrphanSymlink), Some(Cyan.as_fg()));
+        assert_eq!(style(FileKind::CharDevice), Some(Yellow.as_fg()));
+        assert_eq!(style(FileKind::File), Some(Yellow.as_fg()));
+    }
+
+    #[test]
+    fn overrides_and_errors() {
+        let ls_colors =
+            LsColors::parse("di=34:ex=32:di=1:ex=:*.a=31:*.a=:lc=\\e[:ln=target:xx=1:junk:fi=1;zz");
+        assert_eq!(
+            ls_colors.indicator(Indicator::Directory),
+            Some(Style::new().bold())
+        );
+        // Neither `ex` nor `*.a` is set any more, so `fi` applies.
+        assert_eq!(
+            ls_colors.style_for("x.a", &file(0o755)),
+            Some(Style::new().bold())
+        );
+        assert!(ls_colors.links_as_target());
+        assert_eq!(
+            ls_colors.indicator(Indicator::File),
+            Some(Style::new().bold())
+        );
+        assert_eq!(ls_colors.invalid_entries(), ["xx=1", "junk", "fi=1;zz"]);
+    }
+
+    #[test]
+    fn zero_padded_codes() {
+        let ls_colors = LsColors::parse("di=00;01;04;38;05;208:ex=0:fi=001");
+        assert_eq!(
+            ls_colors.indicator(Indicator::Directory),
+            Some(Fixed(208).bold().underline())
+        );
+        assert_eq!(
+            ls_colors.indicator(Indicator::Executable),
+            Some(Style::new())
+        );
+        assert_eq!(
+            ls_colors.indicator(Indicator::File),
+            Some(Style::new().bold())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn from_metadata() {
+        let meta = FileMeta::from(&std::fs::metadata("src").unwrap());
+        assert_eq!(meta.kind, FileKind::Directory);
+        assert_eq!(meta.mode & 0o700, 0o700);
+
+        let ls_colors = LsColors::parse(DIRCOLORS);
+        assert_eq!(ls_colors.style_for("src", &meta), Some(Blue.bold()));
+    }
+}
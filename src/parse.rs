@@ -0,0 +1,785 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::display::{AnsiGenericString, AnsiGenericStrings, OSControl};
+use crate::style::{Color, FormatFlags, Style, UnderlineKind};
+use crate::write::Content;
+
+const ESC: u8 = 0x1B;
+const BEL: u8 = 0x07;
+
+/// String-like data which can be scanned for ANSI escape sequences.
+///
+/// Escape sequences are made up entirely of ASCII bytes, so slicing around
+/// them never splits a UTF-8 encoded character.
+pub trait ParseInput: ToOwned {
+    /// View this input as bytes.
+    fn as_bytes(&self) -> &[u8];
+
+    /// Get the sub-slice of this input covering the given byte range.
+    fn slice(&self, range: Range<usize>) -> &Self;
+}
+
+impl ParseInput for str {
+    fn as_bytes(&self) -> &[u8] {
+        str::as_bytes(self)
+    }
+
+    fn slice(&self, range: Range<usize>) -> &Self {
+        &self[range]
+    }
+}
+
+impl ParseInput for [u8] {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn slice(&self, range: Range<usize>) -> &Self {
+        &self[range]
+    }
+}
+
+/// The kinds of escape sequences which [`parse_ansi`] does not understand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnknownSequenceKind {
+    /// An escape sequence which is neither a CSI nor an OSC sequence.
+    Escape,
+    /// A CSI sequence other than SGR (for example, cursor movement).
+    Csi,
+    /// An SGR sequence containing parameters that could not be interpreted.
+    /// The parameters which were understood have still been applied.
+    Sgr(Vec<String>),
+    /// An OSC sequence other than a window title or a hyperlink.
+    Osc,
+    /// A sequence cut short by the end of the input.
+    Unterminated,
+}
+
+/// An escape sequence found by [`parse_ansi`] that could not be (completely)
+/// turned into styling.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownSequence {
+    /// Byte range of the sequence within the parsed input.
+    pub range: Range<usize>,
+    /// What sort of sequence this is.
+    pub kind: UnknownSequenceKind,
+}
+
+/// The result of parsing text containing ANSI escape sequences.
+pub struct ParsedAnsi<'a, S: 'a + ToOwned + ?Sized> {
+    /// The text of the input, split into differently styled strings.
+    pub strings: AnsiGenericStrings<'a, S>,
+    /// Escape sequences that were skipped, or only partially applied.
+    pub unknown: Vec<UnknownSequence>,
+}
+
+/// The formats turned off by `24` (and `4:0`).
+const UNDERLINES: FormatFlags = FormatFlags::UNDERLINE.union(FormatFlags::DOUBLY_UNDERLINED);
+
+/// Error produced by [`decode_sgr`] when some parameters are not understood.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SgrError {
+    /// The style produced by applying all the parameters that were understood.
+    pub partial: Style,
+    /// The parameters that were not understood.
+    pub unsupported: Vec<String>,
+}
+
+/// Apply the parameters of an SGR sequence (the part between `ESC [` and `m`)
+/// on top of the `base` style.
+///
+/// Both `;` and `:` separated extended colors are understood, as are
+/// zero-padded codes (such as those produced with the `gnu_legacy` feature).
+///
+/// ```
+/// use procr_ansi_term::{decode_sgr, Color, Style};
+///
+/// assert_eq!(decode_sgr(Style::new(), "01;38;5;208"), Ok(Color::Fixed(208).bold()));
+/// assert_eq!(decode_sgr(Color::Red.bold(), "22;39"), Ok(Style::new()));
+/// ```
+pub fn decode_sgr(base: Style, params: &str) -> Result<Style, SgrError> {
+    let mut style = base;
+    let mut unsupported = Vec::new();
+    let mut params = params.split(';');
+
+    while let Some(param) = params.next() {
+        let mut sub_params = param.split(':');
+        let code = match parse_code(sub_params.next().unwrap_or_default()) {
+            Some(code) => code,
+            None => {
+                unsupported.push(param.to_string());
+                continue;
+            }
+        };
+        let sub_params: Vec<&str> = sub_params.collect();
+
+        let applied = match code {
+            0 => Some(Style::new()),
+            1 => Some(style.bold()),
+            2 => Some(style.dimmed()),
+            3 => Some(style.italic()),
+            4 => match sub_params.first().map(|s| parse_code(s)) {
+                None => Some(style.with_underline_kind(UnderlineKind::Single)),
+                Some(Some(0)) => Some(style.remove_formats(UNDERLINES)),
+                Some(Some(code)) => {
+                    UnderlineKind::from_code(code).map(|k| style.with_underline_kind(k))
+                }
+                Some(None) => None,
+            },
+            5 => Some(style.blink()),
+            6 => Some(style.rapid_blink()),
+            7 => Some(style.reverse()),
+            8 => Some(style.hidden()),
+            9 => Some(style.strikethrough()),
+            20 => Some(style.fraktur()),
+            21 => Some(style.doubly_underlined()),
+            22 => Some(style.remove_formats(FormatFlags::BOLD | FormatFlags::DIMMED)),
+            23 => Some(style.remove_formats(FormatFlags::ITALIC | FormatFlags::FRAKTUR)),
+            24 => Some(style.remove_formats(UNDERLINES)),
+            25 => Some(style.remove_formats(FormatFlags::BLINK | FormatFlags::RAPID_BLINK)),
+            27 => Some(style.without_reverse()),
+            28 => Some(style.without_hidden()),
+            29 => Some(style.without_strikethrough()),
+            51 => Some(style.framed()),
+            52 => Some(style.encircled()),
+            53 => Some(style.overline()),
+            54 => Some(style.remove_formats(FormatFlags::FRAMED | FormatFlags::ENCIRCLED)),
+            55 => Some(style.without_overline()),
+            73 => Some(style.superscript()),
+            74 => Some(style.subscript()),
+            75 => Some(style.remove_formats(FormatFlags::SUPERSCRIPT | FormatFlags::SUBSCRIPT)),
+            30..=37 | 90..=97 => named_color(code % 10, code >= 90).map(|c| style.fg(c)),
+            40..=47 | 100..=107 => named_color(code % 10, code >= 100).map(|c| style.bg(c)),
+            39 => Some(style.set_fg(None)),
+            49 => Some(style.set_bg(None)),
+            59 => Some(style.set_underline_color(None)),
+            38 | 48 | 58 => {
+                let color = if sub_params.is_empty() {
+                    extended_color_semicolons(&mut params)
+                } else {
+                    extended_color_colons(&sub_params)
+                };
+                match (color, code) {
+                    (Some(c), 38) => Some(style.fg(c)),
+                    (Some(c), 48) => Some(style.bg(c)),
+                    (Some(c), _) => Some(style.underline_color(c)),
+                    (None, _) => None,
+                }
+            }
+            _ => None,
+        };
+
+        match applied {
+            Some(next) => style = next,
+            None => unsupported.push(param.to_string()),
+        }
+    }
+
+    if unsupported.is_empty() {
+        Ok(style)
+    } else {
+        Err(SgrError {
+            partial: style,
+            unsupported,
+        })
+    }
+}
+
+/// Parse a single numeric parameter. An empty parameter is equivalent to `0`.
+fn parse_code(s: &str) -> Option<u16> {
+    if s.is_empty() {
+        Some(0)
+    } else if s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn parse_channel(s: Option<&str>) -> Option<u8> {
+    parse_code(s?).and_then(|n| u8::try_from(n).ok())
+}
+
+fn named_color(index: u16, bright: bool) -> Option<Color> {
+    let color = match (index, bright) {
+        (0, false) => Color::Black,
+        (1, false) => Color::Red,
+        (2, false) => Color::Green,
+        (3, false) => Color::Yellow,
+        (4, false) => Color::Blue,
+        (5, false) => Color::Purple,
+        (6, false) => Color::Cyan,
+        (7, false) => Color::White,
+        (0, true) => Color::DarkGray,
+        (1, true) => Color::LightRed,
+        (2, true) => Color::LightGreen,
+        (3, true) => Color::LightYellow,
+        (4, true) => Color::LightBlue,
+        (5, true) => Color::LightPurple,
+        (6, true) => Color::LightCyan,
+        (7, true) => Color::LightGray,
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// Extended colors in the common `38;5;n` and `38;2;r;g;b` forms.
+fn extended_color_semicolons<'p>(params: &mut impl Iterator<Item = &'p str>) -> Option<Color> {
+    match parse_code(params.next()?)? {
+        5 => parse_channel(params.next()).map(Color::Fixed),
+        2 => Some(Color::Rgb(
+            parse_channel(params.next())?,
+            parse_channel(params.next())?,
+            parse_channel(params.next())?,
+        )),
+        _ => None,
+    }
+}
+
+/// Extended colors in the ITU T.416 `38:5:n` and `38:2:[id]:r:g:b` forms.
+fn extended_color_colons(sub_params: &[&str]) -> Option<Color> {
+    match (parse_code(sub_params[0])?, &sub_params[1..]) {
+        (5, [n]) => parse_channel(Some(n)).map(Color::Fixed),
+        (2, [r, g, b]) | (2, [_, r, g, b]) => Some(Color::Rgb(
+            parse_channel(Some(r))?,
+            parse_channel(Some(g))?,
+            parse_channel(Some(b))?,
+        )),
+        _ => None,
+    }
+}
+
+/// The escape sequences which are meaningful while building strings.
+enum Sequence {
+    Sgr(Range<usize>),
+    /// A hyperlink, with the range of its parameters and of its url, or the
+    /// end of a hyperlink.
+    Link(Option<(Range<usize>, Range<usize>)>),
+    Title(Range<usize>),
+    Unknown(UnknownSequenceKind),
+}
+
+/// Scan the escape sequence starting at `start` (which must be an `ESC`
+/// byte), returning the position just after it.
+fn scan_escape(bytes: &[u8], start: usize) -> (usize, Sequence) {
+    match bytes.get(start + 1) {
+        None => (
+            bytes.len(),
+            Sequence::Unknown(UnknownSequenceKind::Unterminated),
+        ),
+        Some(b'[') => scan_csi(bytes, start),
+        Some(b']') => scan_osc(bytes, start),
+        Some(0x20..=0x2F) => {
+            let mut end = start + 1;
+            while let Some(0x20..=0x2F) = bytes.get(end) {
+                end += 1;
+            }
+            match bytes.get(end) {
+                Some(0x30..=0x7E) => (end + 1, Sequence::Unknown(UnknownSequenceKind::Escape)),
+                None => (end, Sequence::Unknown(UnknownSequenceKind::Unterminated)),
+                Some(_) => (end, Sequence::Unknown(UnknownSequenceKind::Escape)),
+            }
+        }
+        Some(0x30..=0x7E) => (start + 2, Sequence::Unknown(UnknownSequenceKind::Escape)),
+        // Anything else (control characters, non-ASCII bytes) is left alone,
+        // and only the `ESC` byte itself is skipped.
+        Some(_) => (start + 1, Sequence::Unknown(UnknownSequenceKind::Escape)),
+    }
+}
+
+fn scan_csi(bytes: &[u8], start: usize) -> (usize, Sequence) {
+    let params_start = start + 2;
+    let mut end = params_start;
+    while let Some(0x30..=0x3F) = bytes.get(end) {
+        end += 1;
+    }
+    let params_end = end;
+    while let Some(0x20..=0x2F) = bytes.get(end) {
+        end += 1;
+    }
+    match bytes.get(end) {
+        None => (end, Sequence::Unknown(UnknownSequenceKind::Unterminated)),
+        Some(b'm') if end == params_end => (end + 1, Sequence::Sgr(params_start..params_end)),
+        Some(0x40..=0x7E) => (end + 1, Sequence::Unknown(UnknownSequenceKind::Csi)),
+        Some(_) => (end, Sequence::Unknown(UnknownSequenceKind::Csi)),
+    }
+}
+
+fn scan_osc(bytes: &[u8], start: usize) -> (usize, Sequence) {
+    let payload_start = start + 2;
+    let mut end = payload_start;
+    let (payload_end, seq_end) = loop {
+        match bytes.get(end) {
+            None => {
+                return (
+                    bytes.len(),
+                    Sequence::Unknown(UnknownSequenceKind::Unterminated),
+                )
+            }
+            Some(&BEL) => break (end, end + 1),
+            Some(&ESC) if bytes.get(end + 1) == Some(&b'\\') => break (end, end + 2),
+            // An escape which does not form a string terminator aborts the
+            // OSC sequence.
+            Some(&ESC) => return (end, Sequence::Unknown(UnknownSequenceKind::Osc)),
+            Some(_) => end += 1,
+        }
+    };
+
+    let payload = &bytes[payload_start..payload_end];
+    let sequence = match payload.iter().position(|&b| b == b';') {
+        Some(sep) => {
+            let arg_start = payload_start + sep + 1;
+            match &payload[..sep] {
+                b"0" | b"2" => Sequence::Title(arg_start..payload_end),
+                b"8" => {
+                    // The hyperlink parameters come before the url.
+                    match bytes[arg_start..payload_end]
+                        .iter()
+                        .position(|&b| b == b';')
+                    {
+                        Some(url_sep) => {
+                            let url_start = arg_start + url_sep + 1;
+                            Sequence::Link(
+                                (url_start < payload_end)
+                                    .then_some((arg_start..url_start - 1, url_start..payload_end)),
+                            )
+                        }
+                        None => Sequence::Unknown(UnknownSequenceKind::Osc),
+                    }
+                }
+                _ => Sequence::Unknown(UnknownSequenceKind::Osc),
+            }
+        }
+        None => Sequence::Unknown(UnknownSequenceKind::Osc),
+    };
+    (seq_end, sequence)
+}
+
+/// Split the parameters of a hyperlink into its `id` and the other
+/// `key=value` pairs, decoding any `%`-encoded characters. Parameters without
+/// a `=` are ignored.
+fn link_params(params: &[u8]) -> (Option<String>, BTreeMap<String, String>) {
+    let mut id = None;
+    let mut others = BTreeMap::new();
+    for param in params.split(|&b| b == b':') {
+        let Some(eq) = param.iter().position(|&b| b == b'=') else {
+            continue;
+        };
+        let (key, value) = (
+            percent_decode(&param[..eq]),
+            percent_decode(&param[eq + 1..]),
+        );
+        if key == "id" {
+            id = Some(value);
+        } else {
+            others.insert(key, value);
+        }
+    }
+    (id, others)
+}
+
+/// Decode `%XX` escapes in the given bytes. Anything which is not a valid
+/// escape is kept as it is.
+fn percent_decode(bytes: &[u8]) -> String {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut ix = 0;
+    while ix < bytes.len() {
+        let escaped = bytes
+            .get(ix + 1..ix + 3)
+            .filter(|_| bytes[ix] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                ix += 3;
+            }
+            None => {
+                decoded.push(bytes[ix]);
+                ix += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Parse text containing ANSI escape sequences back into styled strings.
+///
+/// SGR sequences (colors and formatting), OSC 8 hyperlinks and OSC 0/2 window
+/// titles are understood. Any other escape sequence is left out of the
+/// resulting strings, and reported in [`ParsedAnsi::unknown`].
+///
+/// ```
+/// use procr_ansi_term::{parse_ansi, AnsiStrings, Color::{Blue, Red}};
+///
+/// let output = AnsiStrings([Red.paint("red "), Blue.bold().paint("blue")]).to_string();
+/// let parsed = parse_ansi(output.as_str());
+/// assert!(parsed.unknown.is_empty());
+/// assert_eq!(parsed.strings.to_string(), output);
+/// ```
+pub fn parse_ansi<S: ParseInput + ?Sized>(input: &S) -> ParsedAnsi<'_, S> {
+    let bytes = input.as_bytes();
+    let mut strings = Vec::new();
+    let mut unknown = Vec::new();
+    let mut style = Style::new();
+    let mut link: Option<(Range<usize>, Range<usize>)> = None;
+
+    let content_of = |range: Range<usize>| Content::StrLike(Cow::Borrowed(input.slice(range)));
+    let push_text = |strings: &mut Vec<_>, style, link: &Option<(Range<usize>, _)>, range| {
+        let oscontrol = link.clone().map(|(params, url)| {
+            let (id, params) = link_params(&bytes[params]);
+            OSControl::Link {
+                url: content_of(url),
+                id,
+                params,
+            }
+        });
+        strings.push(AnsiGenericString::new(style, content_of(range), oscontrol));
+    };
+
+    let mut text_start = 0;
+    let mut ix = 0;
+    while ix < bytes.len() {
+        if bytes[ix] != ESC {
+            ix += 1;
+            continue;
+        }
+
+        if text_start < ix {
+            push_text(&mut strings, style, &link, text_start..ix);
+        }
+        let (end, sequence) = scan_escape(bytes, ix);
+        match sequence {
+            Sequence::Sgr(params) => {
+                let decoded = std::str::from_utf8(&bytes[params])
+                    .map_err(|_| SgrError {
+                        partial: style,
+                        unsupported: Vec::new(),
+                    })
+                    .and_then(|params| decode_sgr(style, params));
+                style = match decoded {
+                    Ok(decoded) => decoded,
+                    Err(SgrError {
+                        partial,
+                        unsupported,
+                    }) => {
+                        unknown.push(UnknownSequence {
+                            range: ix..end,
+                            kind: UnknownSequenceKind::Sgr(unsupported),
+                        });
+                        partial
+                    }
+                };
+            }
+            Sequence::Link(url) => link = url,
+            Sequence::Title(title) => strings.push(AnsiGenericString::new(
+                style,
+                content_of(title),
+                Some(OSControl::Title),
+            )),
+            Sequence::Unknown(kind) => unknown.push(UnknownSequence {
+                range: ix..end,
+                kind,
+            }),
+        }
+        ix = end;
+        text_start = end;
+    }
+    if text_start < bytes.len() {
+        push_text(&mut strings, style, &link, text_start..bytes.len());
+    }
+
+    ParsedAnsi {
+        strings: AnsiGenericStrings::from_iter(strings),
+        unknown,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::style::Color::*;
+    use crate::{AnsiByteStrings, AnsiStrings};
+
+    fn styles(parsed: &ParsedAnsi<'_, str>) -> Vec<Style> {
+        parsed.strings.iter().map(|s| *s.style_ref()).collect()
+    }
+
+    fn round_trip(strings: AnsiStrings<'_>) {
+        let output = strings.to_string();
+        let parsed = parse_ansi(output.as_str());
+        assert!(parsed.unknown.is_empty(), "{:?}", parsed.unknown);
+        assert_eq!(parsed.strings.to_string(), output);
+    }
+
+    #[test]
+    fn plain_text() {
+        let parsed = parse_ansi("no escapes here");
+        assert_eq!(styles(&parsed), [Style::new()]);
+        assert_eq!(parsed.strings.to_string(), "no escapes here");
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(AnsiStrings([
+            Red.paint("red "),
+            Red.bold().paint("red bold "),
+            Blue.on_bg(Fixed(236)).paint("blue on grey "),
+            Style::new().paint("plain"),
+        ]));
+        round_trip(AnsiStrings([
+            Rgb(70, 130, 180).underline().paint("steel"),
+            Fixed(100).italic().strikethrough().paint(" olive"),
+        ]));
+        round_trip(AnsiStrings([
+            Green.paint("Before link. "),
+            Blue.underline()
+                .paint("Link to example.com.")
+                .hyperlink("https://example.com"),
+            Green.paint(" After link."),
+        ]));
+        round_trip(AnsiStrings([
+            Red.underline().paint("single "),
+            Red.curly_underline().paint("curly "),
+            Blue.dashed_underline().paint("dashed "),
+            Style::new().double_underline().paint("double"),
+        ]));
+        round_trip(AnsiStrings([
+            Style::new()
+                .curly_underline()
+                .underline_color(Fixed(9))
+                .paint("lint "),
+            Red.underline().underline_color(Rgb(1, 2, 3)).paint("rgb "),
+            Red.underline().paint("plain"),
+        ]));
+        round_trip(AnsiStrings([
+            AnsiGenericString::title("My Title"),
+            Green.paint(" After is Green."),
+        ]));
+    }
+
+    #[test]
+    fn colors() {
+        let parsed = parse_ansi("\x1B[38;5;208;48;2;1;2;3mx\x1B[38:2::4:5:6;48:5:17my\x1B[0m");
+        assert_eq!(
+            styles(&parsed),
+            [
+                Fixed(208).on_bg(Rgb(1, 2, 3)),
+                Rgb(4, 5, 6).on_bg(Fixed(17))
+            ]
+        );
+        let parsed = parse_ansi("\x1B[94;101mx\x1B[39my\x1B[mz");
+        assert_eq!(
+            styles(&parsed),
+            [LightBlue.on_bg(LightRed), LightRed.as_bg(), Style::new()]
+        );
+    }
+
+    #[test]
+    fn turning_off() {
+        let parsed = parse_ansi("\x1B[1;2;3;4;9mx\x1B[22;24my\x1B[23;29mz");
+        assert_eq!(
+            styles(&parsed),
+            [
+                Style::new()
+                    .bold()
+                    .dimmed()
+                    .italic()
+                    .underline()
+                    .strikethrough(),
+                Style::new().italic().strikethrough(),
+                Style::new(),
+            ]
+        );
+    }
+
+    #[test]
+    fn underline_colors() {
+        let parsed = parse_ansi("\x1B[4;58;5;1mx\x1B[58:2::1:2:3my\x1B[59mz");
+        assert_eq!(
+            styles(&parsed),
+            [
+                Style::new().underline().underline_color(Fixed(1)),
+                Style::new().underline().underline_color(Rgb(1, 2, 3)),
+                Style::new().underline(),
+            ]
+        );
+    }
+
+    #[test]
+    fn underline_kinds() {
+        let curly = Style::new().curly_underline();
+        assert_eq!(decode_sgr(Style::new(), "4:3"), Ok(curly));
+        assert_eq!(
+            decode_sgr(Style::new(), "04:02"),
+            Ok(Style::new().double_underline())
+        );
+        assert_eq!(decode_sgr(curly, "4"), Ok(Style::new().underline()));
+        assert_eq!(decode_sgr(curly, "4:0"), Ok(Style::new()));
+        assert_eq!(
+            decode_sgr(curly, "4:9"),
+            Err(SgrError {
+                partial: curly,
+                unsupported: vec!["4:9".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn extra_attributes() {
+        let all = Style::new()
+            .overline()
+            .rapid_blink()
+            .fraktur()
+            .framed()
+            .encircled()
+            .superscript()
+            .subscript()
+            .doubly_underlined();
+        assert_eq!(decode_sgr(Style::new(), "53;6;20;51;52;73;74;21"), Ok(all));
+        assert_eq!(
+            decode_sgr(all.italic().underline().blink(), "23;24;25;54;55;75"),
+            Ok(Style::new())
+        );
+        round_trip(AnsiStrings([
+            Red.overline().paint("over "),
+            Red.overline().superscript().paint("super "),
+            Red.subscript().fraktur().paint("sub "),
+            Red.framed().encircled().paint("framed "),
+            Red.encircled()
+                .rapid_blink()
+                .doubly_underlined()
+                .paint("circle"),
+        ]));
+    }
+
+    #[test]
+    fn gnu_legacy_codes() {
+        assert_eq!(
+            decode_sgr(Style::new(), "01;04;09"),
+            Ok(Style::new().bold().underline().strikethrough())
+        );
+    }
+
+    #[test]
+    fn hyperlinks() {
+        let parsed = parse_ansi("a\x1B]8;id=1;https://example.com\x07b\x1B[1mc\x1B]8;;\x1B\\d");
+        let urls: Vec<Option<String>> = parsed
+            .strings
+            .iter()
+            .map(|s| s.url_string().map(|u| u.to_string()))
+            .collect();
+        let url = Some("https://example.com".to_string());
+        assert_eq!(urls, [None, url.clone(), url, None]);
+
+        let ids: Vec<Option<&str>> = parsed.strings.iter().map(|s| s.link_id()).collect();
+        assert_eq!(ids, [None, Some("1"), Some("1"), None]);
+    }
+
+    #[test]
+    fn hyperlink_params() {
+        let link = Red
+            .paint("text")
+            .hyperlink("https://example.com")
+            .with_link_id("x")
+            .with_link_param("lang", "en");
+        round_trip(AnsiStrings([link]));
+
+        let escaped = Red
+            .paint("text")
+            .hyperlink("https://example.com")
+            .with_link_id("a:b")
+            .with_link_param("k", "1;2 %é");
+        let output = escaped.to_string();
+        let parsed = parse_ansi(output.as_str());
+        assert!(parsed.unknown.is_empty());
+        let text = parsed.strings.iter().next().unwrap();
+        assert_eq!(text.link_id(), Some("a:b"));
+        match text.oscontrol() {
+            Some(OSControl::Link { params, .. }) => {
+                assert_eq!(params.get("k").map(String::as_str), Some("1;2 %é"))
+            }
+            other => panic!("not a link: {other:?}"),
+        }
+
+        let parsed = parse_ansi("\x1B]8;junk:k=v:id=2;https://example.com\x1B\\text");
+        let text = parsed.strings.iter().next().unwrap();
+        assert_eq!(text.link_id(), Some("2"));
+        match text.oscontrol() {
+            Some(OSControl::Link { params, .. }) => assert_eq!(
+                params.iter().collect::<Vec<_>>(),
+                [(&"k".to_owned(), &"v".to_owned())]
+            ),
+            other => panic!("not a link: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn titles() {
+        let parsed = parse_ansi("\x1B]0;first\x07\x1B]2;second\x1B\\");
+        let titles: Vec<String> = parsed
+            .strings
+            .iter()
+            .map(|s| {
+                assert!(matches!(s.oscontrol(), Some(OSControl::Title)));
+                s.content().to_string()
+            })
+            .collect();
+        assert_eq!(titles, ["first", "second"]);
+    }
+
+    #[test]
+    fn unknown_sequences() {
+        let parsed = parse_ansi("a\x1B[2Jb\x1B]52;c;Zm9v\x07c\x1B(Bd\x1B[1;99;38;5me\x1B]2;oops");
+        assert_eq!(parsed.strings.to_string(), "abcd\x1B[1me\x1B[0m");
+        assert_eq!(
+            parsed.unknown,
+            [
+                UnknownSequence {
+                    range: 1..5,
+                    kind: UnknownSequenceKind::Csi
+                },
+                UnknownSequence {
+                    range: 6..18,
+                    kind: UnknownSequenceKind::Osc
+                },
+                UnknownSequence {
+                    range: 19..22,
+                    kind: UnknownSequenceKind::Escape
+                },
+                UnknownSequence {
+                    range: 23..35,
+                    kind: UnknownSequenceKind::Sgr(vec!["99".to_string(), "38".to_string()])
+                },
+                UnknownSequence {
+                    range: 36..44,
+                    kind: UnknownSequenceKind::Unterminated
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn multibyte_text() {
+        let parsed = parse_ansi("\x1B[31mこんにちは\x1B[0m 🌍");
+        assert_eq!(parsed.strings.to_string(), "\x1B[31mこんにちは\x1B[0m 🌍");
+    }
+
+    #[test]
+    fn bytes() {
+        let input = AnsiByteStrings([
+            Red.paint("red ".as_bytes()),
+            Blue.bold().paint("blue".as_bytes()),
+        ]);
+        let mut output = Vec::new();
+        input.write_to(&mut output).unwrap();
+
+        let parsed = parse_ansi(output.as_slice());
+        assert!(parsed.unknown.is_empty());
+        let mut reparsed = Vec::new();
+        parsed.strings.write_to(&mut reparsed).unwrap();
+        assert_eq!(reparsed, output);
+    }
+}
@@ -1,6 +1,5 @@
 // Code liberally borrowed from here
 // https://github.com/navierr/coloriz
-use std::u32;
 
 /// Represents RGB color with 8-bit channels.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -90,6 +89,12 @@ impl From<(f32, f32, f32)> for Rgb {
     }
 }
 
+impl From<Rgb> for crate::Color {
+    fn from(rgb: Rgb) -> Self {
+        crate::Color::Rgb(rgb.r, rgb.g, rgb.b)
+    }
+}
+
 use crate::ANSIColorCode;
 use crate::TargetGround;
 impl ANSIColorCode for Rgb {
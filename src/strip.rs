@@ -0,0 +1,241 @@
+use std::fmt;
+use std::io;
+use std::ops::Range;
+
+/// Escape character, which starts every escape sequence.
+const ESC: u8 = 0x1B;
+/// Bell character, which can end an OSC sequence.
+const BEL: u8 = 0x07;
+/// "Cancel" and "substitute" characters, which abort any escape sequence.
+const CAN: u8 = 0x18;
+const SUB: u8 = 0x1A;
+
+/// Where the stripper is in the input, which may be partway through an escape
+/// sequence at the end of a write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+enum State {
+    /// Plain text.
+    #[default]
+    Text,
+    /// Just after an `ESC`.
+    Escape,
+    /// In an escape sequence with intermediate bytes, such as `ESC ( B`.
+    EscapeIntermediate,
+    /// In a control sequence (`ESC [`).
+    Csi,
+    /// In a control string (`ESC ]`, `ESC P`, `ESC X`, `ESC ^` or `ESC _`),
+    /// which is ended by a string terminator, or by `BEL` for OSC.
+    String { osc: bool },
+    /// Just after an `ESC` in a control string, which is a string terminator
+    /// if followed by `\`.
+    StringEscape,
+}
+
+impl State {
+    /// Move on to the next state, given the next byte in the input.
+    fn next(self, byte: u8) -> State {
+        match (self, byte) {
+            (State::Text, ESC) => State::Escape,
+            (State::Text, _) => State::Text,
+            (_, CAN | SUB) => State::Text,
+            (State::Escape, b'[') => State::Csi,
+            (State::Escape, b']') => State::String { osc: true },
+            (State::Escape, b'P' | b'X' | b'^' | b'_') => State::String { osc: false },
+            (State::Escape | State::EscapeIntermediate, 0x20..=0x2F) => State::EscapeIntermediate,
+            (State::Escape | State::EscapeIntermediate | State::Csi, ESC) => State::Escape,
+            (State::Escape | State::EscapeIntermediate, 0x30..=0x7E) => State::Text,
+            (State::Csi, 0x40..=0x7E) => State::Text,
+            (State::String { osc: true }, BEL) => State::Text,
+            (State::String { .. }, ESC) => State::StringEscape,
+            (State::StringEscape, b'\\') => State::Text,
+            // Any other escape sequence aborts the control string.
+            (State::StringEscape, _) => State::Escape.next(byte),
+            (state, _) => state,
+        }
+    }
+
+    /// Whether `byte` is outside the grammar of the escape sequence in
+    /// progress. Like [`parse_ansi`](crate::parse_ansi), this ends the
+    /// sequence just before the byte, which is then plain text.
+    fn ends_before(self, byte: u8) -> bool {
+        match self {
+            State::Text | State::String { .. } => false,
+            State::Escape | State::EscapeIntermediate | State::Csi | State::StringEscape => {
+                !matches!(byte, 0x20..=0x7E | ESC | CAN | SUB)
+            }
+        }
+    }
+}
+
+/// A writer which removes all ANSI escape sequences (SGR styles, hyperlinks,
+/// titles and so on) from what is written to it, and passes the remaining
+/// text on to the wrapped writer.
+///
+/// It implements [`io::Write`] or [`fmt::Write`] (and so [`AnyWrite`](crate::AnyWrite),
+/// through `dyn io::Write` or `dyn fmt::Write`) when the wrapped writer does.
+/// Escape sequences can be split across writes.
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt;
+/// use procr_ansi_term::{fmt_write, StripWriter};
+/// use procr_ansi_term::Color::Red;
+///
+/// let mut w = StripWriter::new(String::new());
+/// Red.bold()
+///     .paint("error")
+///     .hyperlink("https://example.com")
+///     .write_to_any(fmt_write!(&mut w))
+///     .unwrap();
+/// assert_eq!(w.into_inner(), "error");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StripWriter<W> {
+    inner: W,
+    state: State,
+}
+
+impl<W> StripWriter<W> {
+    /// Wrap the given writer.
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            state: State::Text,
+        }
+    }
+
+    /// Get a reference to the wrapped writer.
+    pub const fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped writer. Writing to it directly
+    /// may confuse the stripping of a sequence which is split across writes.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwrap the wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Whether an escape sequence has been started but not finished.
+    pub fn in_escape_sequence(&self) -> bool {
+        self.state != State::Text
+    }
+
+    /// Call `text` with each run of plain text in `input`, continuing from
+    /// where the previous input left off.
+    fn strip<E>(
+        &mut self,
+        input: &[u8],
+        mut text: impl FnMut(&mut W, Range<usize>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mut start = None;
+        for (ix, &byte) in input.iter().enumerate() {
+            if self.state.ends_before(byte) {
+                self.state = State::Text;
+            }
+            let next = self.state.next(byte);
+            match (start, next == State::Text && self.state == State::Text) {
+                (None, true) => start = Some(ix),
+                (Some(from), false) => {
+                    text(&mut self.inner, from..ix)?;
+                    start = None;
+                }
+                _ => {}
+            }
+            self.state = next;
+        }
+        if let Some(from) = start {
+            text(&mut self.inner, from..input.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: io::Write> io::Write for StripWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.strip(buf, |w, range| w.write_all(&buf[range]))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for StripWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Runs of text are delimited by ASCII bytes, so always fall on
+        // character boundaries.
+        self.strip(s.as_bytes(), |w, range| w.write_str(&s[range]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Color::*;
+    use crate::{fmt_write, io_write, AnsiGenericString, AnsiStrings, Style};
+    use std::fmt::Write as _;
+    use std::io::Write as _;
+
+    fn strip(input: &str) -> String {
+        let mut w = StripWriter::new(String::new());
+        w.write_str(input).unwrap();
+        w.into_inner()
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(strip("plain text\n"), "plain text\n");
+        assert_eq!(strip("\x1B[1;31mred\x1B[0m!"), "red!");
+        assert_eq!(strip("\x1B[38:2::1:2:3mrgb"), "rgb");
+        assert_eq!(strip("\x1B]8;;https://a.b\x1B\\link\x1B]8;;\x1B\\"), "link");
+        assert_eq!(strip("\x1B]2;title\x07after"), "after");
+        assert_eq!(strip("\x1BPq#0;2;0;0;0\x1B\\dcs"), "dcs");
+        assert_eq!(strip("\x1B(Bcharset \x1B7saved"), "charset saved");
+        assert_eq!(strip("\x1B[31\x18aborted"), "aborted");
+        assert_eq!(strip("日本\x1B[4m語\x1B[24m"), "日本語");
+        // An unterminated string is ended by the next escape sequence.
+        assert_eq!(strip("\x1B]0;oops\x1B[1mbold"), "bold");
+        // Bytes which cannot be part of the sequence end it, and are kept.
+        assert_eq!(strip("\x1Bé \x1B(ü \x1B[31ä\x1B]8;;\x1Bö"), "é ü äö");
+        assert_eq!(strip("\x1B[1\tx"), "\tx");
+    }
+
+    #[test]
+    fn split_across_writes() {
+        let input = "a\x1B[38;5;208mb\x1B]8;;url\x1B\\c\x1B]8;;\x07d\x1B[0m";
+        for split in 0..=input.len() {
+            let mut w = StripWriter::new(Vec::new());
+            w.write_all(&input.as_bytes()[..split]).unwrap();
+            w.write_all(&input.as_bytes()[split..]).unwrap();
+            assert_eq!(w.get_ref(), b"abcd", "split at {}", split);
+            assert!(!w.in_escape_sequence());
+        }
+    }
+
+    #[test]
+    fn any_write() {
+        let strings = AnsiStrings([
+            AnsiGenericString::title("title"),
+            Red.on_bg(Blue).paint("one "),
+            Style::new()
+                .underline()
+                .paint("two")
+                .hyperlink("https://example.com"),
+        ]);
+
+        let mut s = StripWriter::new(String::new());
+        strings.write_to_any(fmt_write!(&mut s)).unwrap();
+        assert_eq!(s.get_ref(), "one two");
+
+        let mut bytes = StripWriter::new(Vec::new());
+        strings.write_to_any(io_write!(&mut bytes)).unwrap();
+        assert_eq!(bytes.into_inner(), b"one two");
+    }
+}
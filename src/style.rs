@@ -5,7 +5,12 @@ use paste::paste;
 
 bitflags! {
     #[derive(Clone, Copy, Default, PartialEq, Eq)]
-    pub struct FormatFlags: u16 {
+    #[cfg_attr(
+        feature = "derive_serde_style",
+        derive(serde::Deserialize, serde::Serialize),
+        serde(transparent)
+    )]
+    pub struct FormatFlags: u32 {
         /// Whether this style is bold.
         const BOLD = 1 << 1;
         /// Whether this style is dimmed.
@@ -22,6 +27,24 @@ bitflags! {
         const HIDDEN = 1 << 7;
         /// Whether this style is struckthrough.
         const STRIKETHROUGH = 1 << 8;
+        /// Whether this style is overlined.
+        const OVERLINE = 1 << 9;
+        /// Whether this style is blinking rapidly.
+        const RAPID_BLINK = 1 << 10;
+        /// Whether this style uses a Fraktur (blackletter) font.
+        const FRAKTUR = 1 << 11;
+        /// Whether this style is framed.
+        const FRAMED = 1 << 12;
+        /// Whether this style is encircled.
+        const ENCIRCLED = 1 << 13;
+        /// Whether this style is superscript.
+        const SUPERSCRIPT = 1 << 14;
+        /// Whether this style is subscript.
+        const SUBSCRIPT = 1 << 15;
+        /// Whether this style is doubly underlined, using the code `21`. This
+        /// is independent of [`UnderlineKind::Double`], which is written as
+        /// `4:2` and is more widely supported.
+        const DOUBLY_UNDERLINED = 1 << 16;
     }
 }
 
@@ -37,16 +60,75 @@ impl FormatFlags {
     }
 }
 
+/// The shape of the line drawn under underlined text.
+///
+/// Anything other than [`UnderlineKind::Single`] is written as an `4:n` SGR
+/// code, which is understood by kitty, WezTerm, foot, VTE based terminals
+/// and others. Terminals which do not support it usually draw a single
+/// underline instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "derive_serde_style",
+    derive(serde::Deserialize, serde::Serialize)
+)]
+pub enum UnderlineKind {
+    /// A single straight line (code `4`, or `4:1`).
+    #[default]
+    Single,
+    /// Two straight lines (code `4:2`).
+    Double,
+    /// A wavy line, as often used for spelling mistakes and diagnostics (code
+    /// `4:3`).
+    Curly,
+    /// A dotted line (code `4:4`).
+    Dotted,
+    /// A dashed line (code `4:5`).
+    Dashed,
+}
+
+impl UnderlineKind {
+    /// The sub-parameter following `4` in the SGR code for this kind.
+    pub const fn code(self) -> u8 {
+        match self {
+            UnderlineKind::Single => 1,
+            UnderlineKind::Double => 2,
+            UnderlineKind::Curly => 3,
+            UnderlineKind::Dotted => 4,
+            UnderlineKind::Dashed => 5,
+        }
+    }
+
+    /// The kind with the given sub-parameter, if any. (`0` turns underlining
+    /// off, and so is not a kind.)
+    pub const fn from_code(code: u16) -> Option<UnderlineKind> {
+        match code {
+            1 => Some(UnderlineKind::Single),
+            2 => Some(UnderlineKind::Double),
+            3 => Some(UnderlineKind::Curly),
+            4 => Some(UnderlineKind::Dotted),
+            5 => Some(UnderlineKind::Dashed),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "derive_serde_style",
+    derive(serde::Deserialize, serde::Serialize)
+)]
 pub struct Coloring {
     pub fg: Option<Color>,
     pub bg: Option<Color>,
+    /// The color of the line drawn under underlined text. When not set, the
+    /// line has the foreground color.
+    pub underline: Option<Color>,
 }
 
 impl Coloring {
     /// Check if there are no colors set.
     pub const fn is_empty(&self) -> bool {
-        self.fg.is_none() && self.bg.is_none()
+        self.fg.is_none() && self.bg.is_none() && self.underline.is_none()
     }
 }
 
@@ -69,6 +151,7 @@ impl BasedOn for Coloring {
         Self {
             fg: self.fg.rebase_on(base.fg),
             bg: self.bg.rebase_on(base.bg),
+            underline: self.underline.rebase_on(base.underline),
         }
     }
 }
@@ -108,6 +191,8 @@ pub struct Style {
     pub formats: FormatFlags,
     /// Data regarding the foreground/background color applied by this style.
     pub coloring: Coloring,
+    /// The kind of underline drawn, when [`FormatFlags::UNDERLINE`] is set.
+    pub underline_kind: UnderlineKind,
 }
 
 impl BasedOn for Style {
@@ -116,6 +201,11 @@ impl BasedOn for Style {
             reset_prefix: self.reset_prefix.rebase_on(base.reset_prefix),
             formats: self.formats.rebase_on(base.formats),
             coloring: self.coloring.rebase_on(base.coloring),
+            underline_kind: if self.is_underline() {
+                self.underline_kind
+            } else {
+                base.underline_kind
+            },
         }
     }
 }
@@ -125,6 +215,8 @@ impl PartialEq for Style {
         self.formats.symmetric_difference(other.formats).is_empty()
             && self.is_fg() == other.is_fg()
             && self.is_bg() == other.is_bg()
+            && self.is_underline_color() == other.is_underline_color()
+            && self.get_underline_kind() == other.get_underline_kind()
     }
 }
 
@@ -146,6 +238,7 @@ impl Default for Style {
             reset_prefix: false,
             formats: FormatFlags::empty(),
             coloring: Coloring::default(),
+            underline_kind: UnderlineKind::Single,
         }
     }
 }
@@ -236,7 +329,12 @@ impl Style {
         Style {
             reset_prefix: false,
             formats: FormatFlags::empty(),
-            coloring: Coloring { fg: None, bg: None },
+            coloring: Coloring {
+                fg: None,
+                bg: None,
+                underline: None,
+            },
+            underline_kind: UnderlineKind::Single,
         }
     }
 
@@ -246,6 +344,7 @@ impl Style {
             reset_prefix: self.reset_prefix,
             formats: self.formats.union(formats),
             coloring: self.coloring,
+            underline_kind: self.underline_kind,
         }
     }
 
@@ -255,6 +354,7 @@ impl Style {
             reset_prefix: self.reset_prefix,
             formats: self.formats.intersection(formats.complement()),
             coloring: self.coloring,
+            underline_kind: self.underline_kind,
         }
     }
 
@@ -280,12 +380,106 @@ impl Style {
         BLINK,
         REVERSE,
         HIDDEN,
-        STRIKETHROUGH
+        STRIKETHROUGH,
+        OVERLINE,
+        RAPID_BLINK,
+        FRAKTUR,
+        FRAMED,
+        ENCIRCLED,
+        SUPERSCRIPT,
+        SUBSCRIPT,
+        DOUBLY_UNDERLINED
     );
 
     style_color_methods!(fg);
     style_color_methods!(bg);
 
+    /// Sets the color of the line drawn under underlined text to the provided
+    /// color option (possibly `None`, to use the foreground color), then
+    /// returns it.
+    #[inline]
+    pub const fn set_underline_color(mut self, color: Option<Color>) -> Self {
+        self.coloring.underline = color;
+        self
+    }
+
+    /// Set the color of the line drawn under underlined text (SGR code `58`).
+    ///
+    /// This does not turn underlining on by itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::Color;
+    ///
+    /// let lint = Color::Default.curly_underline().underline_color(Color::Red);
+    /// assert_eq!(lint.paint("oops").to_string(), "\x1B[4:3;39;58;5;1moops\x1B[0m");
+    /// ```
+    #[inline]
+    pub const fn underline_color(self, color: Color) -> Self {
+        self.set_underline_color(Some(color))
+    }
+
+    /// Gets the underline color if it exists.
+    #[inline]
+    pub const fn is_underline_color(&self) -> Option<Color> {
+        self.coloring.underline
+    }
+
+    /// Returns a copy of this style, underlined with the given kind of line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::{Style, UnderlineKind};
+    ///
+    /// let style = Style::new().with_underline_kind(UnderlineKind::Dotted);
+    /// assert_eq!(style.get_underline_kind(), Some(UnderlineKind::Dotted));
+    /// ```
+    pub const fn with_underline_kind(self, kind: UnderlineKind) -> Self {
+        let mut style = self.insert_formats(FormatFlags::UNDERLINE);
+        style.underline_kind = kind;
+        style
+    }
+
+    /// Returns a copy of this style with a double underline.
+    pub const fn double_underline(&self) -> Style {
+        self.with_underline_kind(UnderlineKind::Double)
+    }
+
+    /// Returns a copy of this style with a curly (wavy) underline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use procr_ansi_term::Color;
+    ///
+    /// let squiggle = Color::Red.curly_underline();
+    /// assert_eq!(squiggle.paint("typo").to_string(), "\x1B[4:3;31mtypo\x1B[0m");
+    /// ```
+    pub const fn curly_underline(&self) -> Style {
+        self.with_underline_kind(UnderlineKind::Curly)
+    }
+
+    /// Returns a copy of this style with a dotted underline.
+    pub const fn dotted_underline(&self) -> Style {
+        self.with_underline_kind(UnderlineKind::Dotted)
+    }
+
+    /// Returns a copy of this style with a dashed underline.
+    pub const fn dashed_underline(&self) -> Style {
+        self.with_underline_kind(UnderlineKind::Dashed)
+    }
+
+    /// Gets the kind of underline, if this style is underlined.
+    pub const fn get_underline_kind(&self) -> Option<UnderlineKind> {
+        if self.is_underline() {
+            Some(self.underline_kind)
+        } else {
+            None
+        }
+    }
+
     /// Return true if this `Style` requires no escape codes to be represented.
     ///
     /// # Examples
@@ -310,7 +504,7 @@ impl Style {
     /// Check if style has any coloring.
     #[inline]
     pub const fn has_color(self) -> bool {
-        self.coloring.fg.is_some() || self.coloring.bg.is_some()
+        !self.coloring.is_empty()
     }
 
     /// Get the formatting flags of this style.
@@ -328,7 +522,9 @@ impl Style {
 
     /// Create a copy of this style with the specified `coloring`.
     pub fn coloring(self, coloring: Coloring) -> Self {
-        self.set_fg(coloring.fg).set_bg(coloring.bg)
+        self.set_fg(coloring.fg)
+            .set_bg(coloring.bg)
+            .set_underline_color(coloring.underline)
     }
 
     /// Create a copy of this style, with the styling properties updated using
@@ -348,6 +544,16 @@ impl Style {
                 } else {
                     self.coloring.bg
                 },
+                underline: if self.coloring.underline.is_none() {
+                    other.coloring.underline
+                } else {
+                    self.coloring.underline
+                },
+            },
+            underline_kind: if self.is_underline() {
+                self.underline_kind
+            } else {
+                other.underline_kind
             },
         }
     }
@@ -548,6 +754,30 @@ impl Color {
         Style::new().bg(self).fg(fg)
     }
 
+    /// Returns a `Style` with the foreground color set to this color, and a
+    /// double underline.
+    pub const fn double_underline(self) -> Style {
+        self.as_fg().double_underline()
+    }
+
+    /// Returns a `Style` with the foreground color set to this color, and a
+    /// curly underline.
+    pub const fn curly_underline(self) -> Style {
+        self.as_fg().curly_underline()
+    }
+
+    /// Returns a `Style` with the foreground color set to this color, and a
+    /// dotted underline.
+    pub const fn dotted_underline(self) -> Style {
+        self.as_fg().dotted_underline()
+    }
+
+    /// Returns a `Style` with the foreground color set to this color, and a
+    /// dashed underline.
+    pub const fn dashed_underline(self) -> Style {
+        self.as_fg().dashed_underline()
+    }
+
     color_methods!(
         BOLD,
         DIMMED,
@@ -556,7 +786,15 @@ impl Color {
         BLINK,
         REVERSE,
         HIDDEN,
-        STRIKETHROUGH
+        STRIKETHROUGH,
+        OVERLINE,
+        RAPID_BLINK,
+        FRAKTUR,
+        FRAMED,
+        ENCIRCLED,
+        SUPERSCRIPT,
+        SUBSCRIPT,
+        DOUBLY_UNDERLINED
     );
 }
 
@@ -617,6 +855,26 @@ mod serde_json_tests {
     fn style_serialization() {
         let style = Style::default();
 
-        assert_eq!(serde_json::to_string(&style).unwrap(), "{\"foreground\":null,\"background\":null,\"is_bold\":false,\"is_dimmed\":false,\"is_italic\":false,\"is_underline\":false,\"is_blink\":false,\"is_reverse\":false,\"is_hidden\":false,\"is_strikethrough\":false,\"reset_prefix\":false}".to_string());
+        assert_eq!(serde_json::to_string(&style).unwrap(), "{\"reset_prefix\":false,\"formats\":\"\",\"coloring\":{\"fg\":null,\"bg\":null,\"underline\":null},\"underline_kind\":\"Single\"}".to_string());
+    }
+
+    #[test]
+    fn style_deserialization() {
+        let styles = [
+            Style::default(),
+            Color::Red.bold().italic().on(Color::Rgb(1, 2, 3)),
+            Style::new()
+                .curly_underline()
+                .underline_color(Color::Fixed(9))
+                .reset_prefix(),
+        ];
+
+        for style in styles {
+            let serialized = serde_json::to_string(&style).unwrap();
+            let deserialized: Style = serde_json::from_str(&serialized).unwrap();
+
+            assert_eq!(style, deserialized);
+            assert_eq!(style.is_reset_prefix(), deserialized.is_reset_prefix());
+        }
     }
 }
@@ -0,0 +1,549 @@
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::style::{Color, FormatFlags, Style, UnderlineKind};
+
+/// Format words, and the flags they turn on. The first word for each flag is
+/// the one used when displaying a style.
+const FORMATS: [(&str, FormatFlags); 17] = [
+    ("bold", FormatFlags::BOLD),
+    ("dimmed", FormatFlags::DIMMED),
+    ("dim", FormatFlags::DIMMED),
+    ("italic", FormatFlags::ITALIC),
+    ("underline", FormatFlags::UNDERLINE),
+    ("blink", FormatFlags::BLINK),
+    ("reverse", FormatFlags::REVERSE),
+    ("hidden", FormatFlags::HIDDEN),
+    ("strikethrough", FormatFlags::STRIKETHROUGH),
+    ("overline", FormatFlags::OVERLINE),
+    ("rapid_blink", FormatFlags::RAPID_BLINK),
+    ("fraktur", FormatFlags::FRAKTUR),
+    ("framed", FormatFlags::FRAMED),
+    ("encircled", FormatFlags::ENCIRCLED),
+    ("superscript", FormatFlags::SUPERSCRIPT),
+    ("subscript", FormatFlags::SUBSCRIPT),
+    ("doubly_underlined", FormatFlags::DOUBLY_UNDERLINED),
+];
+
+/// Words for the kinds of underline other than a single line.
+const UNDERLINES: [(&str, UnderlineKind); 4] = [
+    ("double_underline", UnderlineKind::Double),
+    ("curly_underline", UnderlineKind::Curly),
+    ("dotted_underline", UnderlineKind::Dotted),
+    ("dashed_underline", UnderlineKind::Dashed),
+];
+
+/// Color words, and the colors they name.
+const COLORS: [(&str, Color); 19] = [
+    ("black", Color::Black),
+    ("dark_gray", Color::DarkGray),
+    ("red", Color::Red),
+    ("light_red", Color::LightRed),
+    ("green", Color::Green),
+    ("light_green", Color::LightGreen),
+    ("yellow", Color::Yellow),
+    ("light_yellow", Color::LightYellow),
+    ("blue", Color::Blue),
+    ("light_blue", Color::LightBlue),
+    ("purple", Color::Purple),
+    ("light_purple", Color::LightPurple),
+    ("magenta", Color::Magenta),
+    ("light_magenta", Color::LightMagenta),
+    ("cyan", Color::Cyan),
+    ("light_cyan", Color::LightCyan),
+    ("white", Color::White),
+    ("light_gray", Color::LightGray),
+    ("default", Color::Default),
+];
+
+/// The ways in which a style string can be malformed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseStyleErrorKind {
+    /// A word which is neither a format nor a color.
+    UnknownWord,
+    /// A word which looks like a color (a number, `#…` or `rgb(…)`), but is
+    /// out of range or malformed.
+    InvalidColor,
+    /// `on`, `fg:`, `bg:` or `ul:` without a color after it.
+    MissingColor,
+    /// A second foreground or background color.
+    DuplicateColor,
+}
+
+/// Error produced when parsing a [`Style`] or [`Color`] from a string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseStyleError {
+    /// Byte range of the offending token within the parsed string.
+    pub range: Range<usize>,
+    /// The offending token.
+    pub token: String,
+    /// What is wrong with the token.
+    pub kind: ParseStyleErrorKind,
+}
+
+impl ParseStyleError {
+    pub(crate) fn new(token: Token<'_>, kind: ParseStyleErrorKind) -> Self {
+        ParseStyleError {
+            range: token.range(),
+            token: token.text.to_string(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for ParseStyleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self.kind {
+            ParseStyleErrorKind::UnknownWord => "unknown style word",
+            ParseStyleErrorKind::InvalidColor => "invalid color",
+            ParseStyleErrorKind::MissingColor => "expected a color after",
+            ParseStyleErrorKind::DuplicateColor => "color given twice",
+        };
+        write!(
+            f,
+            "{} `{}` at {}..{}",
+            problem, self.token, self.range.start, self.range.end
+        )
+    }
+}
+
+impl std::error::Error for ParseStyleError {}
+
+/// A whitespace separated word of a style string.
+#[derive(Clone, Copy)]
+pub(crate) struct Token<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) start: usize,
+}
+
+impl<'a> Token<'a> {
+    /// The part of this token after its first `len` bytes.
+    fn skip(self, len: usize) -> Token<'a> {
+        Token {
+            text: &self.text[len..],
+            start: self.start + len,
+        }
+    }
+
+    fn range(self) -> Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+}
+
+/// Split `s` on whitespace, keeping parenthesised groups such as
+/// `rgb(1, 2, 3)` in one token.
+pub(crate) fn tokens(s: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut depth = 0usize;
+    for (ix, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() {
+                    tokens.push(Token {
+                        text: &s[start..ix],
+                        start,
+                    });
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(ix);
+    }
+    if let Some(start) = start {
+        tokens.push(Token {
+            text: &s[start..],
+            start,
+        });
+    }
+    tokens
+}
+
+/// Compare a word against one of our (lower snake case) names, ignoring
+/// case, `_` and `-`, so that `light_red`, `light-red` and `LightRed` all
+/// match.
+fn is_word(word: &str, name: &str) -> bool {
+    let mut word = word.chars().filter(|c| !matches!(c, '_' | '-'));
+    let mut name = name.chars().filter(|c| *c != '_');
+    loop {
+        match (word.next(), name.next()) {
+            (None, None) => return true,
+            (Some(w), Some(n)) if w.eq_ignore_ascii_case(&n) => {}
+            _ => return false,
+        }
+    }
+}
+
+fn parse_format(word: &str) -> Option<FormatFlags> {
+    FORMATS
+        .iter()
+        .find(|(name, _)| is_word(word, name))
+        .map(|(_, flag)| *flag)
+}
+
+fn parse_rgb_components(s: &str) -> Option<Color> {
+    let mut components = s.split(',').map(|c| c.trim().parse::<u8>());
+    match (
+        components.next(),
+        components.next(),
+        components.next(),
+        components.next(),
+    ) {
+        (Some(Ok(r)), Some(Ok(g)), Some(Ok(b)), None) => Some(Color::Rgb(r, g, b)),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_hex(hex: &str) -> Option<Color> {
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let component = |ix: usize| u8::from_str_radix(&hex[ix..ix + 2], 16).ok();
+    Some(Color::Rgb(component(0)?, component(2)?, component(4)?))
+}
+
+/// Parse a single color token. Returns `Ok(None)` for words which are not
+/// colors at all, so that callers can go on to try other interpretations.
+fn parse_color(token: Token<'_>) -> Result<Option<Color>, ParseStyleError> {
+    let word = token.text;
+    let invalid = || ParseStyleError::new(token, ParseStyleErrorKind::InvalidColor);
+
+    if let Some(hex) = word.strip_prefix('#') {
+        return parse_hex(hex).map(Some).ok_or_else(invalid);
+    }
+    if word
+        .get(..4)
+        .is_some_and(|p| p.eq_ignore_ascii_case("rgb("))
+    {
+        return word[4..]
+            .strip_suffix(')')
+            .and_then(parse_rgb_components)
+            .map(Some)
+            .ok_or_else(invalid);
+    }
+    if word.bytes().all(|b| b.is_ascii_digit()) {
+        return word
+            .parse()
+            .map(|n| Some(Color::Fixed(n)))
+            .map_err(|_| invalid());
+    }
+    Ok(COLORS
+        .iter()
+        .find(|(name, _)| is_word(word, name))
+        .map(|(_, color)| *color))
+}
+
+fn expect_color(token: Token<'_>) -> Result<Color, ParseStyleError> {
+    parse_color(token)?
+        .ok_or_else(|| ParseStyleError::new(token, ParseStyleErrorKind::InvalidColor))
+}
+
+pub(crate) fn set_color(
+    slot: &mut Option<Color>,
+    color: Color,
+    token: Token<'_>,
+) -> Result<(), ParseStyleError> {
+    if slot.replace(color).is_some() {
+        return Err(ParseStyleError::new(
+            token,
+            ParseStyleErrorKind::DuplicateColor,
+        ));
+    }
+    Ok(())
+}
+
+impl FromStr for Color {
+    type Err = ParseStyleError;
+
+    /// Parse a color from one of the forms produced by its `Display`
+    /// implementation: a name such as `light_red` (in any case, with or
+    /// without `_`), a `Fixed` number from `0` to `255`, `#rrggbb`,
+    /// `rgb(r, g, b)` or `default`.
+    ///
+    /// ```
+    /// use procr_ansi_term::Color;
+    ///
+    /// assert_eq!("LightRed".parse(), Ok(Color::LightRed));
+    /// assert_eq!("236".parse(), Ok(Color::Fixed(236)));
+    /// assert_eq!("#ff8800".parse(), Ok(Color::Rgb(255, 136, 0)));
+    /// assert_eq!("rgb(255, 136, 0)".parse(), Ok(Color::Rgb(255, 136, 0)));
+    /// assert!("orange".parse::<Color>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim_start();
+        let start = s.len() - trimmed.len();
+        let trimmed = trimmed.trim_end();
+        let token = Token {
+            text: trimmed,
+            start,
+        };
+        parse_color(token)?
+            .ok_or_else(|| ParseStyleError::new(token, ParseStyleErrorKind::UnknownWord))
+    }
+}
+
+impl FromStr for Style {
+    type Err = ParseStyleError;
+
+    /// Parse a style from whitespace separated words, such as
+    /// `"bold italic #ff8800 on 236"` or `"underline fg:red bg:default"`.
+    ///
+    /// Each word is one of:
+    ///
+    /// - a format (`bold`, `dimmed` or `dim`, `italic`, `underline`,
+    ///   `blink`, `reverse`, `hidden`, `strikethrough`, `overline`,
+    ///   `rapid_blink`, `fraktur`, `framed`, `encircled`, `superscript`,
+    ///   `subscript` or `doubly_underlined`);
+    /// - a kind of underline (`double_underline`, `curly_underline`,
+    ///   `dotted_underline` or `dashed_underline`);
+    /// - a color, in any form understood by [`Color`]'s `FromStr`, which sets
+    ///   the foreground;
+    /// - `on <color>` or `bg:<color>`, which set the background;
+    /// - `fg:<color>`, which sets the foreground;
+    /// - `ul:<color>`, which sets the underline color;
+    /// - `reset`, which sets [`Style::reset_prefix`].
+    ///
+    /// Words are matched ignoring case. The error points at the first word
+    /// which could not be understood.
+    ///
+    /// ```
+    /// use procr_ansi_term::{Color, Style};
+    ///
+    /// let style: Style = "bold italic #ff8800 on 236".parse().unwrap();
+    /// assert_eq!(style, Color::Rgb(255, 136, 0).bold().italic().on(Color::Fixed(236)));
+    ///
+    /// let error = "bold sparkly red".parse::<Style>().unwrap_err();
+    /// assert_eq!(error.token, "sparkly");
+    /// assert_eq!(error.range, 5..12);
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut style = Style::new();
+        let mut fg = None;
+        let mut bg = None;
+        let mut underline = None;
+        let mut tokens = tokens(s).into_iter();
+
+        while let Some(token) = tokens.next() {
+            let word = token.text;
+            if let Some(flag) = parse_format(word) {
+                style = style.insert_formats(flag);
+            } else if let Some((_, kind)) = UNDERLINES.iter().find(|(name, _)| is_word(word, name))
+            {
+                style = style.with_underline_kind(*kind);
+            } else if is_word(word, "reset") {
+                style = style.reset_prefix();
+            } else if is_word(word, "on") {
+                let color = tokens.next().ok_or_else(|| {
+                    ParseStyleError::new(token, ParseStyleErrorKind::MissingColor)
+                })?;
+                set_color(&mut bg, expect_color(color)?, color)?;
+            } else if let Some(ground) = word.get(..3).filter(|p| {
+                ["fg:", "bg:", "ul:"]
+                    .iter()
+                    .any(|g| p.eq_ignore_ascii_case(g))
+            }) {
+                if word.len() == ground.len() {
+                    return Err(ParseStyleError::new(
+                        token,
+                        ParseStyleErrorKind::MissingColor,
+                    ));
+                }
+                let slot = if ground.eq_ignore_ascii_case("fg:") {
+                    &mut fg
+                } else if ground.eq_ignore_ascii_case("bg:") {
+                    &mut bg
+                } else {
+                    &mut underline
+                };
+                let color = token.skip(ground.len());
+                set_color(slot, expect_color(color)?, token)?;
+            } else if let Some(color) = parse_color(token)? {
+                set_color(&mut fg, color, token)?;
+            } else {
+                return Err(ParseStyleError::new(
+                    token,
+                    ParseStyleErrorKind::UnknownWord,
+                ));
+            }
+        }
+
+        Ok(style.set_fg(fg).set_bg(bg).set_underline_color(underline))
+    }
+}
+
+impl fmt::Display for Color {
+    /// Write this color in the form understood by its `FromStr`: a
+    /// lower snake case name, a `Fixed` number, or `#rrggbb`.
+    ///
+    /// ```
+    /// use procr_ansi_term::Color;
+    ///
+    /// assert_eq!(Color::LightRed.to_string(), "light_red");
+    /// assert_eq!(Color::Fixed(236).to_string(), "236");
+    /// assert_eq!(Color::Rgb(255, 136, 0).to_string(), "#ff8800");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Color::Fixed(n) => write!(f, "{}", n),
+            Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            color => {
+                let (name, _) = COLORS
+                    .iter()
+                    .find(|(_, named)| *named == color)
+                    .expect("every other color is named");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    /// Write this style as words understood by its `FromStr`: formats
+    /// first, then the foreground color, then `on` and the background color,
+    /// then `ul:` and the underline color.
+    ///
+    /// ```
+    /// use procr_ansi_term::{Color, Style};
+    ///
+    /// let style = Color::Red.bold().underline().on(Color::Fixed(236));
+    /// assert_eq!(style.to_string(), "bold underline red on 236");
+    /// assert_eq!(style.to_string().parse(), Ok(style));
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut words = Vec::new();
+        if self.reset_prefix {
+            words.push("reset".to_string());
+        }
+        let mut written = FormatFlags::empty();
+        for (name, flag) in FORMATS {
+            if self.formats.contains(flag) && !written.contains(flag) {
+                written |= flag;
+                let underline = UNDERLINES.iter().find(|(_, kind)| {
+                    flag == FormatFlags::UNDERLINE && *kind == self.underline_kind
+                });
+                words.push(underline.map_or(name, |(name, _)| name).to_string());
+            }
+        }
+        if let Some(fg) = self.coloring.fg {
+            words.push(fg.to_string());
+        }
+        if let Some(bg) = self.coloring.bg {
+            words.push(format!("on {}", bg));
+        }
+        if let Some(underline) = self.coloring.underline {
+            words.push(format!("ul:{}", underline));
+        }
+        f.write_str(&words.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::style::Color::*;
+
+    #[test]
+    fn colors() {
+        for (name, color) in COLORS {
+            assert_eq!(name.parse(), Ok(color));
+            assert_eq!(color.to_string(), name);
+        }
+        assert_eq!("LIGHT-GRAY".parse(), Ok(LightGray));
+        assert_eq!(" 0 ".parse(), Ok(Fixed(0)));
+        assert_eq!("#0A0b0C".parse(), Ok(Rgb(10, 11, 12)));
+        assert_eq!("RGB(1,2,3)".parse(), Ok(Rgb(1, 2, 3)));
+    }
+
+    #[test]
+    fn invalid_colors() {
+        let error = |s: &str| s.parse::<Color>().unwrap_err();
+        assert_eq!(error("256").kind, ParseStyleErrorKind::InvalidColor);
+        assert_eq!(error("#ff88").kind, ParseStyleErrorKind::InvalidColor);
+        assert_eq!(error("rgb(1, 2)").kind, ParseStyleErrorKind::InvalidColor);
+        assert_eq!(
+            error("rgb(1, 2, 300)").kind,
+            ParseStyleErrorKind::InvalidColor
+        );
+        assert_eq!(error(" orange").range, 1..7);
+        assert_eq!(error("orange").kind, ParseStyleErrorKind::UnknownWord);
+    }
+
+    #[test]
+    fn styles() {
+        assert_eq!("".parse(), Ok(Style::new()));
+        assert_eq!(
+            "underline fg:red bg:default".parse(),
+            Ok(Red.underline().on(Default))
+        );
+        assert_eq!(
+            "DIM Strikethrough on rgb(1, 2, 3) blue".parse(),
+            Ok(Blue.dimmed().strikethrough().on(Rgb(1, 2, 3)))
+        );
+        assert_eq!(
+            "reset bold".parse::<Style>().map(|s| s.is_reset_prefix()),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn style_errors() {
+        let error = |s: &str| s.parse::<Style>().unwrap_err();
+
+        let e = error("bold on");
+        assert_eq!(
+            (e.token.as_str(), e.range, e.kind),
+            ("on", 5..7, ParseStyleErrorKind::MissingColor)
+        );
+
+        let e = error("red fg:blue");
+        assert_eq!(
+            (e.token.as_str(), e.range, e.kind),
+            ("fg:blue", 4..11, ParseStyleErrorKind::DuplicateColor)
+        );
+
+        let e = error("bg:#12345z");
+        assert_eq!(
+            (e.token.as_str(), e.range, e.kind),
+            ("#12345z", 3..10, ParseStyleErrorKind::InvalidColor)
+        );
+
+        let e = error("on bold");
+        assert_eq!(
+            (e.token.as_str(), e.range, e.kind),
+            ("bold", 3..7, ParseStyleErrorKind::InvalidColor)
+        );
+
+        assert_eq!(
+            error("italic sparkly").to_string(),
+            "unknown style word `sparkly` at 7..14"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let styles = [
+            Style::new(),
+            Style::new().reset_prefix().bold(),
+            Red.bold().dimmed().italic().underline(),
+            Style::new().blink().reverse().hidden().strikethrough(),
+            Style::new().overline().fraktur().bold(),
+            Red.rapid_blink().framed().encircled(),
+            Style::new().superscript().subscript().doubly_underlined(),
+            Red.curly_underline().italic(),
+            Style::new().dashed_underline(),
+            Red.curly_underline().on(Blue).underline_color(Rgb(1, 2, 3)),
+            Fixed(236).on_bg(Rgb(255, 136, 0)),
+            Style::new().on(LightMagenta),
+            Default.on_bg(Default),
+        ];
+        for style in styles {
+            let parsed: Style = style.to_string().parse().unwrap();
+            assert_eq!(parsed, style, "{}", style);
+            assert_eq!(parsed.is_reset_prefix(), style.is_reset_prefix());
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::fmt::Write;
+
+use crate::display::AnsiStrings;
+use crate::html::{escape_html, hex, is_safe_url};
+use crate::utils::runs;
+use crate::width::str_width;
+use crate::{Color, Palette, Rgb, Style};
+
+/// Renders (possibly multi-line) ANSI strings as a standalone SVG image which
+/// looks like a terminal, for screenshots and snapshot tests.
+///
+/// Text is laid out on a grid of cells, with wide characters taking up two.
+/// Background colors are drawn as rectangles behind the text, bold and italic
+/// text use the font weight and style, and underlined and struck through text
+/// use text decorations. Hyperlinks become `<a>` elements (for the same URLs
+/// as [`HtmlRenderer`](crate::HtmlRenderer)).
+///
+/// # Examples
+///
+/// ```
+/// use procr_ansi_term::{AnsiStrings, SvgRenderer};
+/// use procr_ansi_term::Color::{Green, Red};
+///
+/// let output = AnsiStrings([Red.bold().paint("error"), Green.paint(":\n  ok")]);
+/// let svg = SvgRenderer::new().render(&output);
+/// assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+/// assert!(svg.contains("font-weight=\"bold\">error</text>"));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgRenderer {
+    palette: Palette,
+    font_family: String,
+    font_size: f64,
+    cell_width: f64,
+    line_height: f64,
+    padding: f64,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self {
+            palette: Palette::default(),
+            font_family: "monospace".to_string(),
+            font_size: 14.0,
+            cell_width: 8.4,
+            line_height: 18.0,
+            padding: 10.0,
+        }
+    }
+}
+
+/// Format a length, without needless decimals.
+fn length(x: f64) -> String {
+    let s = format!("{:.2}", x);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// A run of text placed on the grid.
+struct Cell<'r> {
+    row: usize,
+    column: usize,
+    width: usize,
+    text: &'r str,
+    style: Style,
+    url: Option<&'r str>,
+}
+
+impl SvgRenderer {
+    /// A renderer using the xterm palette and a 14px monospace font.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given palette, including its default foreground and background
+    /// colors.
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Use the given font family, which should be monospaced.
+    pub fn with_font_family(mut self, font_family: impl Into<String>) -> Self {
+        self.font_family = font_family.into();
+        self
+    }
+
+    /// Use the given font size, and grid cell size (in pixels).
+    pub fn with_font_size(mut self, font_size: f64, cell_width: f64, line_height: f64) -> Self {
+        self.font_size = font_size;
+        self.cell_width = cell_width;
+        self.line_height = line_height;
+        self
+    }
+
+    /// Use the given space (in pixels) around the text.
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// The palette used by this renderer.
+    pub const fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// The colors a style is drawn with: foreground, then background (if not
+    /// the default background).
+    fn colors(&self, style: Style) -> (Rgb, Option<Rgb>) {
+        let rgb = |color: Option<Color>| color.and_then(|c| self.palette.rgb(c));
+        let fg = rgb(style.is_fg());
+        let bg = rgb(style.is_bg());
+        if style.is_reverse() {
+            (
+                bg.unwrap_or(self.palette.background),
+                Some(fg.unwrap_or(self.palette.foreground)),
+            )
+        } else {
+            (fg.unwrap_or(self.palette.foreground), bg)
+        }
+    }
+
+    /// Render a sequence of ANSI strings as an SVG image. Newlines in the text
+    /// start new rows, and the text is broken up into `<text>` elements
+    /// wherever the ANSI output would change its style.
+    pub fn render(&self, strs: &AnsiStrings) -> String {
+        let runs = runs(strs);
+        let urls: Vec<Option<String>> = runs.iter().map(|run| run.url()).collect();
+
+        let mut cells = Vec::new();
+        let (mut row, mut column, mut columns) = (0, 0, 0);
+        for (run, url) in runs.iter().zip(&urls) {
+            for (ix, line) in run.text.split('\n').enumerate() {
+                if ix > 0 {
+                    row += 1;
+                    column = 0;
+                }
+                let text = line.strip_suffix('\r').unwrap_or(line);
+                let width = str_width(text);
+                if width > 0 {
+                    cells.push(Cell {
+                        row,
+                        column,
+                        width,
+                        text,
+                        style: run.style,
+                        url: url.as_deref().filter(|url| is_safe_url(url)),
+                    });
+                }
+                column += width;
+                columns = columns.max(column);
+            }
+        }
+        // A trailing newline ends the last row, rather than starting another.
+        let rows = match runs.last() {
+            None => 0,
+            Some(last) if last.text.ends_with('\n') => row,
+            Some(_) => row + 1,
+        };
+
+        let width = self.padding * 2.0 + columns as f64 * self.cell_width;
+        let height = self.padding * 2.0 + rows as f64 * self.line_height;
+        let mut font_family = String::new();
+        escape_html(&mut font_family, &self.font_family);
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" font-family=\"{}\" font-size=\"{}\" xml:space=\"preserve\">",
+            font_family,
+            length(self.font_size),
+            w = length(width),
+            h = length(height),
+        );
+        let _ = writeln!(
+            svg,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+            hex(self.palette.background)
+        );
+
+        let x = |column: usize| length(self.padding + column as f64 * self.cell_width);
+        let top = |row: usize| self.padding + row as f64 * self.line_height;
+        for cell in &cells {
+            if let (_, Some(bg)) = self.colors(cell.style) {
+                let _ = writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    x(cell.column),
+                    length(top(cell.row)),
+                    length(cell.width as f64 * self.cell_width),
+                    length(self.line_height),
+                    hex(bg),
+                );
+            }
+        }
+
+        // The baseline leaves room for descenders, with the text roughly
+        // centered in its row.
+        let baseline = (self.line_height + self.font_size * 0.7) / 2.0;
+        for cell in &cells {
+            if cell.style.is_hidden() {
+                continue;
+            }
+            if let Some(url) = cell.url {
+                svg.push_str("<a href=\"");
+                escape_html(&mut svg, url);
+                svg.push_str("\">");
+            }
+
+            let (fg, _) = self.colors(cell.style);
+            let _ = write!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\" fill=\"{}\"",
+                x(cell.column),
+                length(top(cell.row) + baseline),
+                length(cell.width as f64 * self.cell_width),
+                hex(fg),
+            );
+            if cell.style.is_bold() {
+                svg.push_str(" font-weight=\"bold\"");
+            }
+            if cell.style.is_italic() {
+                svg.push_str(" font-style=\"italic\"");
+            }
+            if cell.style.is_dimmed() {
+                svg.push_str(" opacity=\"0.5\"");
+            }
+            match (cell.style.is_underline(), cell.style.is_strikethrough()) {
+                (true, true) => svg.push_str(" text-decoration=\"underline line-through\""),
+                (true, false) => svg.push_str(" text-decoration=\"underline\""),
+                (false, true) => svg.push_str(" text-decoration=\"line-through\""),
+                (false, false) => {}
+            }
+            svg.push('>');
+            escape_html(&mut svg, cell.text);
+            svg.push_str("</text>");
+
+            if cell.url.is_some() {
+                svg.push_str("</a>");
+            }
+            svg.push('\n');
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+impl<'a> AnsiStrings<'a> {
+    /// Render these strings as an SVG image which looks like a terminal (see
+    /// [`SvgRenderer`]).
+    pub fn to_svg(&self) -> String {
+        SvgRenderer::new().render(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Color::{Blue, Green, Red, White};
+
+    #[test]
+    fn grid() {
+        let output = AnsiStrings([
+            Style::new().paint("ab\n"),
+            Red.on_bg(White).paint("漢字"),
+            Style::new().paint("c\r\n\n"),
+        ]);
+        let svg = SvgRenderer::new().with_padding(0.0).render(&output);
+        let lines: Vec<&str> = svg.lines().collect();
+
+        // Five columns and three rows.
+        assert_eq!(
+            lines[0],
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"42\" height=\"54\" \
+             viewBox=\"0 0 42 54\" font-family=\"monospace\" font-size=\"14\" xml:space=\"preserve\">"
+        );
+        assert_eq!(
+            lines[1],
+            "<rect width=\"100%\" height=\"100%\" fill=\"#000000\"/>"
+        );
+        assert_eq!(
+            lines[2],
+            "<rect x=\"0\" y=\"18\" width=\"33.6\" height=\"18\" fill=\"#e5e5e5\"/>"
+        );
+        assert_eq!(
+            lines[3],
+            "<text x=\"0\" y=\"13.9\" textLength=\"16.8\" lengthAdjust=\"spacingAndGlyphs\" \
+             fill=\"#e5e5e5\">ab</text>"
+        );
+        assert_eq!(
+            lines[4],
+            "<text x=\"0\" y=\"31.9\" textLength=\"33.6\" lengthAdjust=\"spacingAndGlyphs\" \
+             fill=\"#cd0000\">漢字</text>"
+        );
+        assert_eq!(
+            lines[5],
+            "<text x=\"33.6\" y=\"31.9\" textLength=\"8.4\" lengthAdjust=\"spacingAndGlyphs\" \
+             fill=\"#e5e5e5\">c</text>"
+        );
+        assert_eq!(lines[6], "</svg>");
+    }
+
+    #[test]
+    fn formats_and_links() {
+        let output = AnsiStrings([
+            Blue.bold().italic().paint("<b>"),
+            Green
+                .underline()
+                .strikethrough()
+                .paint("link")
+                .hyperlink("https://example.com/?a&b"),
+            Style::new().hidden().paint("secret"),
+            Style::new().reverse().paint("r"),
+        ]);
+        let svg = output.to_svg();
+
+        assert!(svg.contains(
+            "fill=\"#0000ee\" font-weight=\"bold\" font-style=\"italic\">&lt;b&gt;</text>"
+        ));
+        assert!(svg.contains(
+            "<a href=\"https://example.com/?a&amp;b\"><text x=\"35.2\" y=\"23.9\" \
+             textLength=\"33.6\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#00cd00\" \
+             text-decoration=\"underline line-through\">link</text></a>"
+        ));
+        assert!(!svg.contains("secret"));
+        // Reversed default colors swap the palette's defaults.
+        assert!(svg.contains("width=\"8.4\" height=\"18\" fill=\"#e5e5e5\"/>"));
+        assert!(svg.contains("fill=\"#000000\">r</text>"));
+    }
+}
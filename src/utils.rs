@@ -1,6 +1,19 @@
-use crate::display::AnsiStrings;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::difference::StyleDelta;
+use crate::display::{AnsiString, AnsiStrings, OSControl};
+use crate::parse::parse_ansi;
+use crate::style::{BasedOn, Style};
+use crate::width::{grapheme_width, graphemes};
+use crate::write::Content;
 
 /// Return a substring of the given AnsiStrings sequence, while keeping the formatting.
+///
+/// `start` and `len` are byte offsets into the unstyled text, and this panics
+/// if they do not fall on character boundaries: see [`sub_string_by`] for a
+/// substring in characters, grapheme clusters or terminal columns.
 pub fn sub_string<'a>(start: usize, len: usize, strs: &AnsiStrings) -> AnsiStrings<'a> {
     let mut vec = Vec::new();
     let mut pos = start;
@@ -20,7 +33,11 @@ pub fn sub_string<'a>(start: usize, len: usize, strs: &AnsiStrings) -> AnsiStrin
         let end = pos + len_rem;
         let pos_end = if end >= frag_len { frag_len } else { end };
 
-        vec.push(i.style_ref().paint(String::from(&content[pos..pos_end])));
+        vec.push(AnsiString::new(
+            *i.style_ref(),
+            Content::StrLike(Cow::Owned(String::from(&content[pos..pos_end]))),
+            i.oscontrol().as_ref().map(owned_oscontrol),
+        ));
 
         if end <= frag_len {
             break;
@@ -45,6 +62,10 @@ pub fn unstyle(strs: &AnsiStrings) -> String {
 }
 
 /// Return the unstyled length of AnsiStrings. This is equaivalent to `unstyle(strs).len()`.
+///
+/// Note that this is a length in bytes: use
+/// [`AnsiStrings::display_width`](crate::AnsiGenericStrings::display_width) for
+/// the number of columns taken up in a terminal.
 pub fn unstyled_len(strs: &AnsiStrings) -> usize {
     let mut l = 0;
     for i in strs.iter() {
@@ -53,10 +74,349 @@ pub fn unstyled_len(strs: &AnsiStrings) -> usize {
     l
 }
 
+/// Return a copy of the given AnsiStrings sequence in which every hyperlink
+/// without an `id` parameter is given one.
+///
+/// Terminals treat strings with the same url and id as a single link, so
+/// doing this before splitting text up with [`sub_string_by`] or
+/// [`AnsiStrings::wrap`](crate::AnsiGenericStrings::wrap) lets the pieces of a
+/// link be highlighted together. The ids are a hash of the url and of how many
+/// times that url was linked to before, so they are the same every time the
+/// same strings are given.
+///
+/// # Examples
+///
+/// ```
+/// use procr_ansi_term::AnsiStrings;
+/// use procr_ansi_term::Color::Blue;
+/// use procr_ansi_term::utils::with_link_ids;
+///
+/// let text = AnsiStrings([Blue.paint("the manual page").hyperlink("https://example.com")]);
+/// let lines = with_link_ids(&text).wrap(10);
+/// let ids: Vec<_> = lines.iter().flat_map(|line| line.iter()).map(|s| s.link_id()).collect();
+/// assert_eq!(ids.len(), 2);
+/// assert!(ids[0].is_some());
+/// assert_eq!(ids[0], ids[1]);
+/// ```
+pub fn with_link_ids<'a>(strs: &AnsiStrings<'a>) -> AnsiStrings<'a> {
+    link_ids_in(strs, &mut HashMap::new())
+}
+
+fn link_ids_in<'a>(strs: &AnsiStrings<'a>, seen: &mut HashMap<String, u64>) -> AnsiStrings<'a> {
+    let strings: AnsiStrings<'a> = strs
+        .iter()
+        .map(|s| {
+            let mut s = s.clone();
+            if let Some(OSControl::Link { url, id: None, .. }) = s.oscontrol() {
+                let url = url.to_string();
+                let count = seen.entry(url.clone()).or_insert(0);
+                let id = link_id(&url, *count);
+                *count += 1;
+                s = s.with_link_id(id);
+            }
+            if let Content::GenericStrings(inner) = &s.content {
+                s.content = Content::GenericStrings(link_ids_in(inner, seen));
+            }
+            s
+        })
+        .collect();
+    strings.with_delta_strategy(strs.delta_strategy())
+}
+
+/// The id given by [`with_link_ids`] to the link to `url` which comes after
+/// `count` other links to it: an FNV-1a hash, which unlike the hasher of the
+/// standard library is guaranteed not to change.
+fn link_id(url: &str, count: u64) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let hash = url
+        .bytes()
+        .chain(count.to_le_bytes())
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        });
+    format!("{hash:016x}")
+}
+
+/// The unit in which [`sub_string_by`] measures positions in text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Unit {
+    /// Unicode scalar values (`char`s).
+    Chars,
+    /// Grapheme clusters (see [`graphemes`](crate::width::graphemes)).
+    Graphemes,
+    /// Terminal columns (see [`str_width`](crate::width::str_width)).
+    Columns,
+}
+
+impl Unit {
+    /// Split text into pieces of this unit, as the byte range of each piece
+    /// along with its size in this unit.
+    fn split(self, text: &str) -> Vec<(Range<usize>, usize)> {
+        match self {
+            Unit::Chars => text
+                .char_indices()
+                .map(|(ix, c)| (ix..ix + c.len_utf8(), 1))
+                .collect(),
+            Unit::Graphemes | Unit::Columns => {
+                let mut ix = 0;
+                graphemes(text)
+                    .map(|g| {
+                        let range = ix..ix + g.len();
+                        ix = range.end;
+                        let size = if self == Unit::Columns {
+                            grapheme_width(g)
+                        } else {
+                            1
+                        };
+                        (range, size)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Return the part of the given AnsiStrings sequence which starts at `start`
+/// and is `len` long, both measured in the given [`Unit`], while keeping the
+/// formatting.
+///
+/// Nested strings are flattened, with each piece of text keeping the style it
+/// is displayed with, as well as its hyperlink. Characters are never split:
+/// when measuring in columns, a wide character which only partly falls in
+/// the range is left out. Window titles take up no space, so are left out too.
+///
+/// # Examples
+///
+/// ```
+/// use procr_ansi_term::AnsiStrings;
+/// use procr_ansi_term::Color::{Green, Red};
+/// use procr_ansi_term::utils::{sub_string_by, Unit};
+///
+/// let strings = AnsiStrings([Red.paint("日本語"), Green.paint("テキスト")]);
+///
+/// let chars = sub_string_by(Unit::Chars, 2, 2, &strings);
+/// assert_eq!(chars.to_string(), AnsiStrings([Red.paint("語"), Green.paint("テ")]).to_string());
+///
+/// let columns = sub_string_by(Unit::Columns, 3, 4, &strings);
+/// assert_eq!(columns.to_string(), Red.paint("語").to_string());
+/// ```
+pub fn sub_string_by<'a>(
+    unit: Unit,
+    start: usize,
+    len: usize,
+    strs: &AnsiStrings<'a>,
+) -> AnsiStrings<'a> {
+    let end = start.saturating_add(len);
+    let mut pos = 0;
+    let mut vec = Vec::new();
+
+    for fragment in fragments(strs.iter()) {
+        if pos >= end {
+            break;
+        }
+        if !fragment.is_displayed() {
+            continue;
+        }
+
+        let mut taken: Option<Range<usize>> = None;
+        for (bytes, size) in unit.split(&fragment.text) {
+            let included = pos >= start && pos + size <= end && (size > 0 || pos < end);
+            pos += size;
+            match (included, &mut taken) {
+                (true, Some(range)) => range.end = bytes.end,
+                (true, None) => taken = Some(bytes),
+                (false, Some(_)) => break,
+                (false, None) => {}
+            }
+        }
+
+        if let Some(range) = taken {
+            vec.push(fragment.slice(range));
+        }
+    }
+
+    AnsiStrings(vec)
+}
+
+/// A run of text with a single style and OS control, as it would appear on
+/// the terminal once all nesting has been resolved.
+pub(crate) struct Fragment<'a> {
+    /// The style of the text, including the styles it inherits from the
+    /// strings it is nested in.
+    pub(crate) style: Style,
+    /// The text, without any escape sequences.
+    pub(crate) text: Cow<'a, str>,
+    /// The link or title this text belongs to, if any.
+    pub(crate) oscontrol: Option<OSControl<'a, str>>,
+}
+
+impl<'a> Fragment<'a> {
+    /// Whether this text is shown in the terminal's text area (rather than,
+    /// say, being a window title).
+    pub(crate) fn is_displayed(&self) -> bool {
+        !matches!(self.oscontrol, Some(OSControl::Title))
+    }
+
+    /// The URL this text links to, if any.
+    pub(crate) fn url(&self) -> Option<String> {
+        match &self.oscontrol {
+            Some(OSControl::Link { url, .. }) => Some(url.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Whether this text belongs to the same link as `other` (or neither of
+    /// them is a link).
+    fn same_link(&self, other: &Fragment<'_>) -> bool {
+        match (&self.oscontrol, &other.oscontrol) {
+            (
+                Some(OSControl::Link { id, params, .. }),
+                Some(OSControl::Link {
+                    id: other_id,
+                    params: other_params,
+                    ..
+                }),
+            ) => self.url() == other.url() && id == other_id && params == other_params,
+            _ => self.url().is_none() && other.url().is_none(),
+        }
+    }
+
+    /// An ANSI string holding the given byte range of this text, with the
+    /// same style and OS control.
+    pub(crate) fn slice(&self, range: Range<usize>) -> AnsiString<'a> {
+        let text = match &self.text {
+            Cow::Borrowed(text) => Cow::Borrowed(&text[range]),
+            Cow::Owned(text) => Cow::Owned(text[range].to_owned()),
+        };
+        AnsiString::new(self.style, Content::StrLike(text), self.oscontrol.clone())
+    }
+}
+
+/// Flatten the given strings into [`Fragment`]s, resolving nested strings and
+/// stripping escape sequences which were written into the text itself.
+pub(crate) fn fragments<'b, 'a: 'b>(
+    strs: impl IntoIterator<Item = &'b AnsiString<'a>>,
+) -> Vec<Fragment<'a>> {
+    let mut out = Vec::new();
+    for s in strs {
+        push_fragments(s, Style::new(), None, &mut out);
+    }
+    out
+}
+
+/// Flatten the given strings into the runs of text which are displayed,
+/// following the same [`StyleDelta`]s which are written out as ANSI codes.
+///
+/// A new run starts wherever a delta is written (or the link changes), so
+/// that other renderers break up the text exactly where the ANSI output
+/// does. Nested strings and escape sequences in the text are resolved within
+/// the run of the string they belong to.
+pub(crate) fn runs<'a>(strs: &AnsiStrings<'a>) -> Vec<Fragment<'a>> {
+    let mut runs: Vec<Fragment<'a>> = Vec::new();
+    let mut style = Style::new();
+    for (delta, content, oscontrol) in strs.write_iter() {
+        style = delta.apply_to(style);
+        let mut fragments = Vec::new();
+        push_content(&content, style, oscontrol.as_ref(), &mut fragments);
+
+        let mut continues = delta == StyleDelta::Empty;
+        for fragment in fragments {
+            if !fragment.is_displayed() || fragment.text.is_empty() {
+                continue;
+            }
+            match runs.last_mut() {
+                Some(last)
+                    if continues && last.style == fragment.style && last.same_link(&fragment) =>
+                {
+                    last.text.to_mut().push_str(&fragment.text);
+                }
+                _ => runs.push(fragment),
+            }
+            continues = true;
+        }
+    }
+    runs
+}
+
+fn push_fragments<'a>(
+    s: &AnsiString<'a>,
+    outer: Style,
+    outer_oscontrol: Option<&OSControl<'a, str>>,
+    out: &mut Vec<Fragment<'a>>,
+) {
+    let style = s.style_ref().rebase_on(outer);
+    let oscontrol = s.oscontrol().as_ref().or(outer_oscontrol);
+    push_content(s.content(), style, oscontrol, out);
+}
+
+/// Push the fragments of `content`, which is shown in the given `style`.
+fn push_content<'a>(
+    content: &Content<'a, str>,
+    style: Style,
+    oscontrol: Option<&OSControl<'a, str>>,
+    out: &mut Vec<Fragment<'a>>,
+) {
+    match content {
+        Content::StrLike(text) if !text.contains('\x1B') => out.push(Fragment {
+            style,
+            text: text.clone(),
+            oscontrol: oscontrol.cloned(),
+        }),
+        Content::StrLike(text) => push_parsed(text, style, oscontrol, out),
+        Content::FmtArgs(args) => push_parsed(&args.to_string(), style, oscontrol, out),
+        Content::Renderer(renderer) => push_parsed(&renderer.render(), style, oscontrol, out),
+        Content::GenericStrings(strs) => {
+            for inner in strs.iter() {
+                push_fragments(inner, style, oscontrol, out);
+            }
+        }
+    }
+}
+
+/// Push the fragments of text which may contain escape sequences.
+fn push_parsed<'a>(
+    text: &str,
+    outer: Style,
+    outer_oscontrol: Option<&OSControl<'a, str>>,
+    out: &mut Vec<Fragment<'a>>,
+) {
+    for parsed in parse_ansi(text).strings.iter() {
+        let oscontrol = match parsed.oscontrol() {
+            Some(oscontrol) => Some(owned_oscontrol(oscontrol)),
+            None => outer_oscontrol.cloned(),
+        };
+        out.push(Fragment {
+            style: parsed.style_ref().rebase_on(outer),
+            text: Cow::Owned(parsed.content().to_string()),
+            oscontrol,
+        });
+    }
+}
+
+/// Copy an OS control so that it no longer borrows from the string it
+/// came from.
+fn owned_oscontrol<'a>(oscontrol: &OSControl<'_, str>) -> OSControl<'a, str> {
+    match oscontrol {
+        OSControl::Link { url, id, params } => OSControl::Link {
+            url: Content::StrLike(Cow::Owned(url.to_string())),
+            id: id.clone(),
+            params: params.clone(),
+        },
+        OSControl::Title => OSControl::Title,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::Color::*;
+    use crate::Style;
+
+    fn assert_same(actual: AnsiStrings, expected: AnsiStrings) {
+        assert_eq!(actual.to_string(), expected.to_string());
+    }
 
     #[test]
     fn test_unstyling() {
@@ -81,4 +441,150 @@ mod test {
         let l2 = AnsiStrings([Black.paint("st"), Red.paint("-second"), White.paint("-t")]);
         assert_eq!(&sub_string(3, 11, &l).to_string(), &l2.to_string());
     }
+
+    #[test]
+    fn test_substring_keeps_links() {
+        let url = "https://example.com";
+        let l = AnsiStrings([
+            Red.paint("see "),
+            Blue.paint("the manual").hyperlink(url).with_link_id("m"),
+        ]);
+        assert_same(
+            sub_string(2, 7, &l),
+            AnsiStrings([
+                Red.paint("e "),
+                Blue.paint("the m").hyperlink(url).with_link_id("m"),
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_link_ids() {
+        let strings = || {
+            AnsiStrings([
+                Red.paint("a").hyperlink("https://a.example"),
+                Red.paint("b").hyperlink("https://b.example"),
+                Red.paint("a again").hyperlink("https://a.example"),
+                Red.paint("named")
+                    .hyperlink("https://a.example")
+                    .with_link_id("mine"),
+                Red.paint("plain"),
+                Red.paint(AnsiStrings([Blue
+                    .paint("nested")
+                    .hyperlink("https://a.example")])),
+            ])
+        };
+        let with_ids = with_link_ids(&strings());
+        let ids: Vec<Option<&str>> = with_ids.iter().map(|s| s.link_id()).collect();
+        assert!(ids[..3].iter().all(Option::is_some));
+        assert_ne!(ids[0], ids[1]);
+        assert_ne!(ids[0], ids[2]);
+        assert_eq!(&ids[3..], [Some("mine"), None, None]);
+        assert_eq!(ids[0], Some(link_id("https://a.example", 0).as_str()));
+
+        let nested = match &with_ids.iter().last().unwrap().content {
+            Content::GenericStrings(inner) => inner.iter().next().unwrap().link_id(),
+            _ => panic!("nested strings were flattened"),
+        };
+        assert_eq!(nested, Some(link_id("https://a.example", 2).as_str()));
+
+        // The same strings always get the same ids.
+        assert_eq!(with_link_ids(&strings()).to_string(), with_ids.to_string());
+    }
+
+    #[test]
+    fn test_substring_by() {
+        let l = AnsiStrings([
+            Red.paint("héllo "),
+            Blue.bold().paint("wörld").hyperlink("https://example.com"),
+        ]);
+
+        assert_same(
+            sub_string_by(Unit::Chars, 1, 7, &l),
+            AnsiStrings([
+                Red.paint("éllo "),
+                Blue.bold().paint("wö").hyperlink("https://example.com"),
+            ]),
+        );
+        assert_same(sub_string_by(Unit::Chars, 20, 2, &l), AnsiStrings([]));
+        assert_same(sub_string_by(Unit::Graphemes, 0, 0, &l), AnsiStrings([]));
+
+        let accents = AnsiStrings([Green.paint("e\u{301}e\u{301}"), Red.paint("👍🏽x")]);
+        assert_same(
+            sub_string_by(Unit::Graphemes, 1, 2, &accents),
+            AnsiStrings([Green.paint("e\u{301}"), Red.paint("👍🏽")]),
+        );
+        assert_same(
+            sub_string_by(Unit::Chars, 1, 2, &accents),
+            AnsiStrings([Green.paint("\u{301}e")]),
+        );
+    }
+
+    #[test]
+    fn test_substring_by_columns() {
+        let l = AnsiStrings([Red.paint("ab"), Green.paint("漢字"), Blue.paint("cd")]);
+
+        // Wide characters which only partly fall in the range are left out.
+        assert_same(
+            sub_string_by(Unit::Columns, 1, 4, &l),
+            AnsiStrings([Red.paint("b"), Green.paint("漢")]),
+        );
+        assert_same(
+            sub_string_by(Unit::Columns, 3, 5, &l),
+            AnsiStrings([Green.paint("字"), Blue.paint("cd")]),
+        );
+    }
+
+    #[test]
+    fn test_substring_by_nested() {
+        let link = Cyan.paint("link").hyperlink("https://example.com");
+        let l = AnsiStrings([Red.underline().paint(AnsiStrings([
+            Style::new().paint("-"),
+            Blue.bold().paint(AnsiStrings([link])),
+        ]))]);
+
+        assert_same(
+            sub_string_by(Unit::Chars, 0, 3, &l),
+            AnsiStrings([
+                Red.underline().paint("-"),
+                Cyan.bold()
+                    .underline()
+                    .paint("li")
+                    .hyperlink("https://example.com"),
+            ]),
+        );
+
+        let fmt = Red.paint(format!("{}!", Blue.bold().paint("ok")));
+        assert_same(
+            sub_string_by(Unit::Chars, 1, 2, &AnsiStrings([fmt])),
+            AnsiStrings([Blue.bold().paint("k"), Red.paint("!")]),
+        );
+    }
+
+    #[test]
+    fn test_runs() {
+        let strs = AnsiStrings([
+            AnsiString::title("title"),
+            Red.paint("a"),
+            Red.paint(AnsiStrings([Style::new().paint("b")])),
+            Blue.paint("c"),
+        ]);
+        let texts: Vec<_> = runs(&strs)
+            .into_iter()
+            .map(|run| (run.style, run.text.into_owned()))
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                (Red.as_fg(), "ab".to_owned()),
+                (Blue.as_fg(), "c".to_owned())
+            ]
+        );
+
+        // Styles inherited through `rebase_on` are the ones written out.
+        let strs = AnsiStrings([Red.bold().paint("a"), Style::new().paint("b")])
+            .rebase_on(Style::new().bold());
+        let styles: Vec<Style> = runs(&strs).iter().map(|run| run.style).collect();
+        assert_eq!(styles, [Red.bold(), Style::new().bold()]);
+    }
 }
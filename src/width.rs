@@ -0,0 +1,313 @@
+use crate::display::{AnsiString, AnsiStrings};
+use crate::unicode_tables::{grapheme_category, in_table, GraphemeCategory, WIDE, ZERO_WIDTH};
+use crate::utils::fragments;
+
+/// The number of terminal columns taken up by a single character, when it is
+/// displayed on its own.
+///
+/// Control characters, combining marks and other zero-width characters take
+/// up no columns, East Asian wide and fullwidth characters (including most
+/// emoji) take up two, and everything else takes up one.
+///
+/// ```
+/// use procr_ansi_term::width::char_width;
+///
+/// assert_eq!(char_width('a'), 1);
+/// assert_eq!(char_width('漢'), 2);
+/// assert_eq!(char_width('\u{301}'), 0);
+/// ```
+pub fn char_width(c: char) -> usize {
+    if c.is_ascii() {
+        // Printable ASCII is the common case, and never needs a lookup.
+        usize::from(!c.is_ascii_control())
+    } else if c.is_control() || in_table(c, ZERO_WIDTH) {
+        0
+    } else if in_table(c, WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+/// The number of terminal columns taken up by a grapheme cluster (see
+/// [`graphemes`]).
+///
+/// A cluster takes up the width of its first character, except that emoji
+/// presentation sequences (those containing `U+FE0F`) and pairs of regional
+/// indicators (flags) take up two columns.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    let mut chars = grapheme.chars();
+    let Some(first) = chars.next() else {
+        return 0;
+    };
+    let width = char_width(first);
+    let is_flag = grapheme_category(first) == Some(GraphemeCategory::RegionalIndicator)
+        && chars.clone().next().is_some();
+    if width != 0 && (is_flag || chars.any(|c| c == '\u{FE0F}')) {
+        2
+    } else {
+        width
+    }
+}
+
+/// The number of terminal columns taken up by the given text, which should
+/// not contain escape sequences.
+///
+/// ```
+/// use procr_ansi_term::width::str_width;
+///
+/// assert_eq!(str_width("hello"), 5);
+/// assert_eq!(str_width("こんにちは"), 10);
+/// assert_eq!(str_width("e\u{301}"), 1);
+/// assert_eq!(str_width("👩‍🔬"), 2);
+/// ```
+pub fn str_width(s: &str) -> usize {
+    if s.is_ascii() {
+        s.bytes().filter(|b| !b.is_ascii_control()).count()
+    } else {
+        graphemes(s).map(grapheme_width).sum()
+    }
+}
+
+/// The number of terminal columns taken up by the displayed text of the given
+/// strings, measured as a whole so that grapheme clusters may span strings.
+fn strings_width<'b, 'a: 'b>(strs: impl IntoIterator<Item = &'b AnsiString<'a>>) -> usize {
+    let text: String = fragments(strs)
+        .iter()
+        .filter(|fragment| fragment.is_displayed())
+        .map(|fragment| fragment.text.as_ref())
+        .collect();
+    str_width(&text)
+}
+
+impl<'a> AnsiString<'a> {
+    /// The number of terminal columns taken up by this string when it is
+    /// displayed.
+    ///
+    /// Escape sequences, link URLs and window titles take up no space, and
+    /// wide characters and grapheme clusters are taken into account (see
+    /// [`str_width`]).
+    ///
+    /// ```
+    /// use procr_ansi_term::Color::Red;
+    ///
+    /// let link = Red.bold().paint("日本").hyperlink("https://example.com");
+    /// assert_eq!(link.to_string().len(), 50);
+    /// assert_eq!(link.display_width(), 4);
+    /// ```
+    pub fn display_width(&self) -> usize {
+        strings_width(std::iter::once(self))
+    }
+}
+
+impl<'a> AnsiStrings<'a> {
+    /// The number of terminal columns taken up by these strings when they are
+    /// displayed (see [`AnsiString::display_width`]).
+    pub fn display_width(&self) -> usize {
+        strings_width(self.iter())
+    }
+}
+
+/// The break property of a character, as needed by the grapheme cluster
+/// boundary rules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Break {
+    Cr,
+    Lf,
+    Zwj,
+    HangulL,
+    HangulV,
+    HangulT,
+    HangulLv,
+    HangulLvt,
+    Other(Option<GraphemeCategory>),
+}
+
+impl Break {
+    fn of(c: char) -> Break {
+        match c {
+            '\r' => Break::Cr,
+            '\n' => Break::Lf,
+            '\u{200D}' => Break::Zwj,
+            '\u{1100}'..='\u{115F}' | '\u{A960}'..='\u{A97C}' => Break::HangulL,
+            '\u{1160}'..='\u{11A7}' | '\u{D7B0}'..='\u{D7C6}' => Break::HangulV,
+            '\u{11A8}'..='\u{11FF}' | '\u{D7CB}'..='\u{D7FB}' => Break::HangulT,
+            '\u{AC00}'..='\u{D7A3}' => {
+                if (c as u32 - 0xAC00).is_multiple_of(28) {
+                    Break::HangulLv
+                } else {
+                    Break::HangulLvt
+                }
+            }
+            _ => Break::Other(grapheme_category(c)),
+        }
+    }
+
+    fn is_control(self) -> bool {
+        matches!(
+            self,
+            Break::Cr | Break::Lf | Break::Other(Some(GraphemeCategory::Control))
+        )
+    }
+}
+
+/// An iterator over the (extended) grapheme clusters of a string: the
+/// user-perceived characters, such as a letter followed by its combining
+/// accents, or an emoji ZWJ sequence.
+///
+/// Created by [`graphemes`].
+#[derive(Clone, Debug)]
+pub struct Graphemes<'a> {
+    rest: &'a str,
+}
+
+/// Split the given text into grapheme clusters, following the rules of
+/// Unicode Standard Annex #29 (without the `Prepend` and Indic conjunct
+/// rules).
+///
+/// ```
+/// use procr_ansi_term::width::graphemes;
+///
+/// let clusters: Vec<&str> = graphemes("ae\u{301}🇳🇿\r\n").collect();
+/// assert_eq!(clusters, ["a", "e\u{301}", "🇳🇿", "\r\n"]);
+/// ```
+pub fn graphemes(s: &str) -> Graphemes<'_> {
+    Graphemes { rest: s }
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chars = self.rest.char_indices();
+        let (_, first) = chars.next()?;
+
+        let mut previous = Break::of(first);
+        // Whether the cluster so far is an extended pictographic character,
+        // followed by any number of extending characters (rule GB11).
+        let mut pictographic =
+            previous == Break::Other(Some(GraphemeCategory::ExtendedPictographic));
+        // The number of regional indicators seen so far (rules GB12 and GB13).
+        let mut regional_indicators =
+            usize::from(previous == Break::Other(Some(GraphemeCategory::RegionalIndicator)));
+        let mut end = self.rest.len();
+
+        for (ix, c) in chars {
+            let next = Break::of(c);
+            let joined = match (previous, next) {
+                (Break::Cr, Break::Lf) => true,
+                (p, n) if p.is_control() || n.is_control() => false,
+                (
+                    Break::HangulL,
+                    Break::HangulL | Break::HangulV | Break::HangulLv | Break::HangulLvt,
+                ) => true,
+                (Break::HangulLv | Break::HangulV, Break::HangulV | Break::HangulT) => true,
+                (Break::HangulLvt | Break::HangulT, Break::HangulT) => true,
+                (
+                    _,
+                    Break::Zwj
+                    | Break::Other(Some(GraphemeCategory::Extend | GraphemeCategory::SpacingMark)),
+                ) => true,
+                (Break::Zwj, Break::Other(Some(GraphemeCategory::ExtendedPictographic))) => {
+                    pictographic
+                }
+                (
+                    Break::Other(Some(GraphemeCategory::RegionalIndicator)),
+                    Break::Other(Some(GraphemeCategory::RegionalIndicator)),
+                ) => regional_indicators % 2 == 1,
+                _ => false,
+            };
+            if !joined {
+                end = ix;
+                break;
+            }
+
+            match next {
+                Break::Other(Some(GraphemeCategory::Extend)) | Break::Zwj => {}
+                Break::Other(Some(GraphemeCategory::ExtendedPictographic)) => pictographic = true,
+                _ => pictographic = false,
+            }
+            if next == Break::Other(Some(GraphemeCategory::RegionalIndicator)) {
+                regional_indicators += 1;
+            }
+            previous = next;
+        }
+
+        let (grapheme, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(grapheme)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Color::*;
+    use crate::{AnsiGenericString, Style};
+
+    fn split(s: &str) -> Vec<&str> {
+        graphemes(s).collect()
+    }
+
+    #[test]
+    fn grapheme_clusters() {
+        assert_eq!(split(""), Vec::<&str>::new());
+        assert_eq!(split("abc"), ["a", "b", "c"]);
+        assert_eq!(split("\r\n\n"), ["\r\n", "\n"]);
+        assert_eq!(split("a\u{308}\u{301}b"), ["a\u{308}\u{301}", "b"]);
+        assert_eq!(
+            split("\u{1100}\u{1161}\u{11A8}가"),
+            ["\u{1100}\u{1161}\u{11A8}", "가"]
+        );
+        assert_eq!(split("👨‍👩‍👧x"), ["👨‍👩‍👧", "x"]);
+        assert_eq!(split("👍🏽👍"), ["👍🏽", "👍"]);
+        assert_eq!(split("🇦🇺🇳🇿🇫"), ["🇦🇺", "🇳🇿", "🇫"]);
+        assert_eq!(split("a\u{200D}b"), ["a\u{200D}", "b"]);
+        assert_eq!(split("क्षि"), ["क्", "षि"]);
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(str_width("\x07tab\t"), 3);
+        assert_eq!(str_width("日本語テキスト"), 14);
+        assert_eq!(str_width("ｆｕｌｌ"), 8);
+        assert_eq!(str_width("naïve café"), 10);
+        assert_eq!(str_width("a\u{200B}b"), 2);
+        assert_eq!(str_width("👍🏽"), 2);
+        assert_eq!(str_width("❤\u{FE0F}"), 2);
+        assert_eq!(str_width("❤"), 1);
+        assert_eq!(str_width("🇳🇿"), 2);
+        assert_eq!(str_width("한국어"), 6);
+        assert_eq!(str_width("\u{1100}\u{1161}\u{11A8}"), 2);
+        // Unlike `unicode-width`, Jamo Extended-B vowels and finals are zero width.
+        assert_eq!(str_width("\u{A960}\u{D7B0}\u{D7CB}"), 2);
+    }
+
+    #[test]
+    fn display_widths() {
+        let link = Blue
+            .underline()
+            .paint("クリック")
+            .hyperlink("https://example.com/");
+        assert_eq!(link.display_width(), 8);
+
+        let strings = AnsiStrings([
+            AnsiGenericString::title("a long window title"),
+            Red.paint("e"),
+            Green.paint("\u{301}"),
+            Style::new().paint(format!("{}!", Yellow.bold().paint("x"))),
+            link,
+        ]);
+        assert_eq!(strings.display_width(), 11);
+        assert_eq!(
+            Style::new()
+                .paint(format_args!("{}!", Yellow.bold().paint("x")))
+                .display_width(),
+            2
+        );
+
+        let nested = Red.paint(AnsiStrings([Blue.paint("🇳"), Blue.bold().paint("🇿")]));
+        assert_eq!(nested.display_width(), 2);
+        assert_eq!(Red.paint("\x1B[1mbold\x1B[0m").display_width(), 4);
+    }
+}
@@ -3,7 +3,8 @@ use std::fmt;
 use std::fmt::Debug;
 use std::io;
 
-use crate::{AnsiGenericStrings, Style};
+use crate::style::BasedOn;
+use crate::{AnsiGenericString, AnsiGenericStrings, Capabilities, Style};
 
 /// Helper to alias  over [`fmt::Result`], or [`io::Result`] depending on the
 /// error type used ([`fmt::Error`] or [`io::Error`]).
@@ -124,11 +125,71 @@ impl<'a, W: AnyWrite + ?Sized, S: 'a + ?Sized + ToOwned + AsRef<W::Buf>> StrLike
     }
 }
 
+/// Renders a format string whose arguments are ANSI strings, as produced by the
+/// [`ansi_format!`](crate::ansi_format) macro.
+///
+/// Unlike [`fmt::Arguments`], the arguments stay accessible, so that they can
+/// be restyled before rendering. When nested in another string (see
+/// [`Content::with_context`]), the rendered text is styled like an
+/// [`AnsiGenericStrings`] sequence would be.
+pub trait FmtRenderer<'a, S: 'a + ?Sized + ToOwned>: Debug + 'a {
+    /// The ANSI strings used as arguments to the format string.
+    fn render_inputs_ref(&self) -> &[AnsiGenericString<'a, S>];
+
+    /// Mutable access to the ANSI strings used as arguments to the format
+    /// string.
+    fn render_inputs_mut(&mut self) -> &mut [AnsiGenericString<'a, S>];
+
+    /// Clone this renderer into a new box.
+    fn clone_renderer(&self) -> Box<dyn FmtRenderer<'a, S>>;
+
+    /// The style this renderer is nested in, which both the literal text of
+    /// the format string and its arguments are rebased on.
+    fn context(&self) -> Style;
+
+    /// Set the style this renderer is nested in (see
+    /// [`context`](FmtRenderer::context)).
+    fn set_context(&mut self, context: Style);
+
+    /// The rendered format string, as the sequence of ANSI strings (rebased on
+    /// the [`context`](FmtRenderer::context)) which
+    /// [`render_fmt`](FmtRenderer::render_fmt) writes out. Writing it with
+    /// [`AnsiGenericStrings::write_to_any_with`] uses only the escape
+    /// sequences supported by the given capabilities.
+    fn render_strings(&self) -> AnsiGenericStrings<'a, S>;
+
+    /// Write out the format string to the given formatter, with its arguments
+    /// written out (along with their escape sequences) in place.
+    fn render_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// Render the format string into a new `String` (see
+    /// [`render_fmt`](FmtRenderer::render_fmt)).
+    fn render(&self) -> String {
+        /// Displays using the given closure.
+        struct DisplayWith<F>(F);
+
+        impl<F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result> fmt::Display for DisplayWith<F> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                (self.0)(f)
+            }
+        }
+
+        DisplayWith(|f: &mut fmt::Formatter<'_>| self.render_fmt(f)).to_string()
+    }
+}
+
+impl<'a, S: 'a + ?Sized + ToOwned> fmt::Display for dyn FmtRenderer<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.render_fmt(f)
+    }
+}
+
 /// Enumerates the two types of content an [`AnyWrite`] implementor can handle:
 /// * [fmt::Arguments]
 /// * anything that implements [`AsRef<AnyWrite::Buf>`] (conveniently
 ///   stored in either reference or owned format within a [`Cow`]).
 /// * an [`AnsiGenericString`]
+/// * a [`FmtRenderer`]
 pub enum Content<'a, S: 'a + ?Sized + ToOwned> {
     /// Content is [`fmt::Arguments`].
     FmtArgs(fmt::Arguments<'a>),
@@ -139,6 +200,9 @@ pub enum Content<'a, S: 'a + ?Sized + ToOwned> {
     /// [`AnsyGenericString`](crate::AnsiGenericString) can be converted into an
     /// [`AnsiGenericStrings`] using the appropriate [`From`] impl.
     GenericStrings(AnsiGenericStrings<'a, S>),
+    /// Content is rendered from a format string and ANSI string arguments
+    /// (see [`FmtRenderer`]).
+    Renderer(Box<dyn FmtRenderer<'a, S>>),
 }
 
 impl<'a, S: 'a + ?Sized + ToOwned> Content<'a, S> {
@@ -149,27 +213,25 @@ impl<'a, S: 'a + ?Sized + ToOwned> Content<'a, S> {
             x @ Content::FmtArgs(_) => Self::GenericStrings(context.paint(x).into()),
             x @ Content::StrLike(_) => Self::GenericStrings(context.paint(x).into()),
             Content::GenericStrings(x) => Self::GenericStrings(x.rebase_on(context)),
+            Content::Renderer(mut x) => {
+                let context = x.context().rebase_on(context);
+                x.set_context(context);
+                Self::Renderer(x)
+            }
         }
     }
 }
 
-impl<'a, S: 'a + ?Sized + ToOwned> ToString for Content<'a, S>
+impl<'a, S: 'a + ?Sized + ToOwned> fmt::Display for Content<'a, S>
 where
     S: AsRef<str>,
 {
-    fn to_string(&self) -> String {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Content::FmtArgs(x) => format!("{}", x),
-            Content::StrLike(x) => {
-                let mut s = String::new();
-                x.as_ref().write_str_to(fmt_write!(&mut s)).unwrap();
-                s
-            }
-            Content::GenericStrings(x) => {
-                let mut s = String::new();
-                x.write_to_any(fmt_write!(&mut s)).unwrap();
-                s
-            }
+            Content::FmtArgs(x) => write!(f, "{}", x),
+            Content::StrLike(x) => x.as_ref().write_str_to(fmt_write!(f)),
+            Content::GenericStrings(x) => x.write_to_any(fmt_write!(f)),
+            Content::Renderer(x) => x.render_fmt(f),
         }
     }
 }
@@ -180,6 +242,7 @@ impl<'a, S: 'a + ?Sized + ToOwned> Clone for Content<'a, S> {
             Self::FmtArgs(x) => Self::FmtArgs(*x),
             Self::StrLike(x) => Self::StrLike(x.clone()),
             Self::GenericStrings(x) => Self::GenericStrings(x.clone()),
+            Self::Renderer(x) => Self::Renderer(x.clone_renderer()),
         }
     }
 }
@@ -193,6 +256,7 @@ where
             Self::FmtArgs(x) => f.debug_tuple("FmtArgs").field(x).finish(),
             Self::StrLike(x) => f.debug_tuple("StrLike").field(&x.as_ref()).finish(),
             Self::GenericStrings(x) => f.debug_tuple("Ansi").field(&x).finish(),
+            Self::Renderer(x) => f.debug_tuple("Renderer").field(&x).finish(),
         }
     }
 }
@@ -203,6 +267,20 @@ impl<'a, S: 'a + ?Sized + ToOwned> Content<'a, S> {
         &self,
         w: &mut W,
     ) -> WriteResult<W::Error>
+    where
+        S: StrLike<'a, W>,
+        str: AsRef<T>,
+    {
+        self.write_to_with(w, Capabilities::global())
+    }
+
+    /// Write content to the given writer, with any nested ANSI strings using
+    /// only the escape sequences supported by the given `capabilities`.
+    pub fn write_to_with<T: ?Sized + ToOwned, W: AnyWrite<Buf = T> + ?Sized>(
+        &self,
+        w: &mut W,
+        capabilities: Capabilities,
+    ) -> WriteResult<W::Error>
     where
         S: StrLike<'a, W>,
         str: AsRef<T>,
@@ -210,7 +288,8 @@ impl<'a, S: 'a + ?Sized + ToOwned> Content<'a, S> {
         match self {
             Content::FmtArgs(args) => w.write_fmt(*args),
             Content::StrLike(s) => <S as StrLike<'a, W>>::write_str_to(s, w),
-            Content::GenericStrings(x) => x.write_to_any(w),
+            Content::GenericStrings(x) => x.write_to_any_with(w, capabilities),
+            Content::Renderer(x) => x.render_strings().write_to_any_with(w, capabilities),
         }
     }
 }
@@ -236,6 +315,12 @@ impl<'a, S: 'a + ?Sized + ToOwned> From<AnsiGenericStrings<'a, S>> for Content<'
     }
 }
 
+impl<'a, S: 'a + ?Sized + ToOwned> From<Box<dyn FmtRenderer<'a, S>>> for Content<'a, S> {
+    fn from(renderer: Box<dyn FmtRenderer<'a, S>>) -> Self {
+        Content::Renderer(renderer)
+    }
+}
+
 impl<'a> From<String> for Content<'a, str> {
     fn from(s: String) -> Self {
         Content::StrLike(Cow::Owned(s))
@@ -0,0 +1,200 @@
+use std::{fmt, io};
+
+use procr_ansi_term::Color::{Blue, Fixed, Green, Red, Rgb};
+use procr_ansi_term::{
+    ansi_format, ansi_write, ansi_writeln, fmt_write, io_write, AnsiStrings, Capabilities,
+    ColorDepth, OscTerminator, Style,
+};
+
+#[test]
+fn positional() {
+    let renderer = ansi_format!("{} {}!", Red.paint("hello"), Blue.bold().paint("kitty"));
+    assert_eq!(
+        renderer.render(),
+        "\x1B[31mhello\x1B[0m \x1B[1;34mkitty\x1B[0m!"
+    );
+}
+
+#[test]
+fn named_and_implicit() {
+    let name = Green.paint("world");
+    let renderer = ansi_format!(
+        "{greeting}, {name} ({name}) {0}",
+        Style::new().paint("#1"),
+        greeting = Red.paint("hello"),
+    );
+    assert_eq!(
+        renderer.render(),
+        "\x1B[31mhello\x1B[0m, \x1B[32mworld\x1B[0m (\x1B[32mworld\x1B[0m) #1"
+    );
+}
+
+#[test]
+fn nested_in_paint() {
+    let painted = Red.paint(ansi_format!(
+        "{} and {}",
+        Style::new().bold().paint("bold"),
+        Blue.paint("blue")
+    ));
+    // Like other nested strings, the inputs and the text between them are
+    // rebased on the outer style.
+    let nested = Red.paint(AnsiStrings([
+        Style::new().bold().paint("bold"),
+        Style::new().paint(" and "),
+        Blue.paint("blue"),
+    ]));
+    assert_eq!(painted.to_string(), nested.to_string());
+    assert_eq!(
+        painted.to_string(),
+        "\x1B[31m\x1B[1mbold\x1B[0m\x1B[31m and \x1B[34mblue\x1B[0m\x1B[0m"
+    );
+    // The rendered escapes are parsed back when measuring.
+    assert_eq!(AnsiStrings([painted]).display_width(), 13);
+}
+
+#[test]
+fn clone_and_debug() {
+    let renderer = ansi_format!("[{}]", Red.paint("x"));
+    let cloned = renderer.clone_renderer();
+    assert_eq!(cloned.render(), renderer.render());
+    let debug = format!("{:?}", renderer);
+    assert!(debug.contains("render_inputs"));
+    assert!(debug.contains("fmt_s: \"[{}]\""));
+}
+
+#[test]
+fn argument_styles() {
+    let name = "Ferris";
+    let score = Blue.paint("42");
+    let renderer = ansi_format!("{red.bold:name} scored {green.underline:score:>3}");
+    assert_eq!(
+        renderer.render(),
+        format!(
+            "{} scored {}",
+            Red.bold().paint("Ferris"),
+            AnsiStrings([Green.underline().paint(" "), Blue.underline().paint("42")])
+        )
+    );
+}
+
+#[test]
+fn markup() {
+    let renderer = ansi_format!(
+        "[bold]{} [red on 208]x[/] [[y][/] {}",
+        Style::new().paint("a"),
+        Green.paint("b")
+    );
+    assert_eq!(
+        renderer.render(),
+        AnsiStrings([
+            Style::new().bold().paint("a"),
+            Style::new().bold().paint(" "),
+            Red.bold().on(Fixed(208)).paint("x"),
+            Style::new().bold().paint(" [y]"),
+            Style::new().paint(" "),
+            Green.paint("b"),
+        ])
+        .to_string()
+    );
+    assert_eq!(
+        ansi_format!("[1/3] {}", Red.paint("[x]")).render(),
+        format!("[1/3] {}", Red.paint("[x]"))
+    );
+}
+
+#[test]
+fn write_with_capabilities() {
+    let painted = Red.paint(ansi_format!(
+        "[bold]see[/] {}",
+        Rgb(250, 5, 5)
+            .paint("docs")
+            .hyperlink("https://example.com")
+    ));
+    let mut s = String::new();
+    AnsiStrings([painted])
+        .write_to_any_with(
+            fmt_write!(&mut s),
+            Capabilities {
+                color_depth: ColorDepth::Ansi16,
+                hyperlinks: false,
+                titles: false,
+                osc_terminator: OscTerminator::St,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        s,
+        "\x1B[31m\x1B[1msee\x1B[0m\x1B[31m \x1B[91mdocs\x1B[0m\x1B[0m"
+    );
+}
+
+#[test]
+fn display() {
+    let renderer = ansi_format!("[bold]{}[/]!", Red.paint("x"));
+    assert_eq!(format!("{}", renderer), renderer.render());
+}
+
+#[test]
+fn write_macros() {
+    let name = "world";
+
+    let mut s = String::new();
+    ansi_write!(fmt_write!(&mut s), "hello {red:name}").unwrap();
+    ansi_writeln!(fmt_write!(&mut s), "[bold]![/]").unwrap();
+    assert_eq!(s, "hello \x1B[31mworld\x1B[0m\x1B[1m!\x1B[0m\n");
+
+    let mut bytes: Vec<u8> = Vec::new();
+    ansi_writeln!(io_write!(&mut bytes), "hello {}", Red.paint(name)).unwrap();
+    assert_eq!(bytes, b"hello \x1B[31mworld\x1B[0m\n");
+
+    // `ansi_println!` and `ansi_eprintln!` print what `ansi_writeln!` writes;
+    // they are run in the `ansi_format` example.
+    let mut s = String::new();
+    ansi_writeln!(fmt_write!(&mut s), "[dim]{} to stderr[/]", Red.paint(name)).unwrap();
+    assert_eq!(s, "\x1B[2;31mworld\x1B[0m\x1B[2m to stderr\x1B[0m\n");
+}
+
+#[test]
+fn width_and_precision() {
+    let x = Red.paint("x");
+    assert_eq!(
+        ansi_format!("[{:>4}]", x.clone()).render(),
+        format!("[   {}]", x)
+    );
+    assert_eq!(
+        ansi_format!("[{:4}|{:-^5}]", x.clone(), Blue.paint("漢")).render(),
+        format!("[{}   |-{}--]", x, Blue.paint("漢"))
+    );
+    // Padding takes the style given to the argument in the format string.
+    let y = Red.paint("x");
+    assert_eq!(
+        ansi_format!("{on_blue:y:*<3}").render(),
+        AnsiStrings([
+            Red.on_bg(Blue).paint("x"),
+            Style::new().on(Blue).paint("**")
+        ])
+        .to_string()
+    );
+    assert_eq!(
+        ansi_format!("{:.3}|{:>5.2}", Green.paint("styled"), Red.paint("日本語")).render(),
+        format!("{}| {}", Green.paint("sty"), Red.paint("日本"))
+    );
+    // Other format specs are passed on as they are.
+    assert_eq!(ansi_format!("{:?}", x.clone()).render(), format!("{:?}", x));
+}
+
+#[test]
+fn call_site_names() {
+    // The expansion refers to everything by its full path, so it can't
+    // shadow names used in the arguments.
+    struct Style;
+    impl Style {
+        fn paint(text: &str) -> procr_ansi_term::AnsiString<'_> {
+            Red.paint(text)
+        }
+    }
+    assert_eq!(
+        ansi_format!("{}", Style::paint("x")).render(),
+        Red.paint("x").to_string()
+    );
+}
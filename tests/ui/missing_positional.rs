@@ -0,0 +1,6 @@
+use procr_ansi_term::ansi_format;
+
+fn main() {
+    let _ = ansi_format!("{} {} {}", "one");
+    let _ = ansi_format!("{0} {2}", "one", "two");
+}
@@ -0,0 +1,7 @@
+use procr_ansi_term::ansi_format;
+
+fn main() {
+    let x = "x";
+    assert_eq!(ansi_format!("[42] {}", x).to_string(), "[42] x");
+    assert_eq!(ansi_format!("[INFO] {}", x).to_string(), "[INFO] x");
+}
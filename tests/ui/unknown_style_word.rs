@@ -0,0 +1,5 @@
+use procr_ansi_term::ansi_format;
+
+fn main() {
+    let _ = ansi_format!("[bold redd]{blu.italic:x}[/]", x = "x");
+}
@@ -0,0 +1,6 @@
+use procr_ansi_term::ansi_format;
+
+fn main() {
+    let _ = ansi_format!("{}", "used", "unused");
+    let _ = ansi_format!("{a}", a = "used", b = "unused");
+}